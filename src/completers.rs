@@ -1,8 +1,12 @@
 use clap_complete::CompletionCandidate;
 use clap_lex::OsStrExt;
-use thermite::{api::get_package_index, core::find_mods};
+use thermite::core::find_mods;
 
-use crate::{config::CONFIG, model::ModName, utils::GroupedMods};
+use crate::{
+    config::CONFIG,
+    model::{ModName, PackageIndex},
+    utils::{GroupedMods, find_enabled_mods, find_unmanaged},
+};
 
 pub fn profiles(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
     let prefix = CONFIG
@@ -24,6 +28,37 @@ pub fn profiles(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
         .collect()
 }
 
+pub fn templates(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
+    let mut candidates: Vec<CompletionCandidate> = CONFIG
+        .templates()
+        .iter()
+        .filter(|(name, _)| name.starts_with(&*current.to_string_lossy()))
+        .map(|(name, template)| {
+            CompletionCandidate::new(name)
+                .help(template.description.as_ref().map(|d| d.clone().into()))
+        })
+        .collect();
+
+    candidates.extend(profiles(current));
+    candidates
+}
+
+pub fn presets(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
+    let Some(profile) = CONFIG.current_profile_dir() else {
+        return vec![];
+    };
+
+    let Ok(presets) = super::preset::find_presets(profile) else {
+        return vec![];
+    };
+
+    presets
+        .into_iter()
+        .filter(|preset| preset.starts_with(&*current.to_string_lossy()))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
 pub fn installed_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
     let Some(current) = current.to_str() else {
         return vec![];
@@ -48,6 +83,41 @@ pub fn installed_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
         .collect()
 }
 
+/// Installed packages, plus the mods in the legacy `mods` folder
+pub fn removable_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
+    let mut res = installed_mods(current);
+    res.extend(unmanaged_mods(current, None));
+    res
+}
+
+/// Mods in the legacy `mods` folder, optionally only those that are enabled or disabled
+fn unmanaged_mods(current: &std::ffi::OsStr, enabled: Option<bool>) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+
+    let current = current.to_lowercase();
+
+    let Ok(prefix) = CONFIG.install_dir() else {
+        return vec![];
+    };
+    let Ok(mods) = find_unmanaged(&prefix) else {
+        return vec![];
+    };
+    let enabled_mods = find_enabled_mods(&prefix);
+
+    mods.into_iter()
+        .map(|(_, json)| json.name)
+        .filter(|name| {
+            enabled.is_none_or(|state| {
+                enabled_mods.as_ref().is_none_or(|em| em.is_enabled(name)) == state
+            })
+        })
+        .filter(|name| name.to_lowercase().starts_with(&current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
 pub fn mod_index(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
     let Some(current) = current.to_str() else {
         return vec![];
@@ -55,11 +125,16 @@ pub fn mod_index(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
 
     let current = current.to_lowercase();
 
-    let Ok(mods) = get_package_index() else {
+    // only go to the network if papa has never fetched the index before
+    let Ok(index) = PackageIndex::cached().or_else(|_| PackageIndex::fetch()) else {
         return vec![];
     };
+    let base = current
+        .split_once('@')
+        .map_or(current.as_str(), |(base, _)| base);
 
-    mods.iter()
+    index
+        .complete(base)
         .map(|m| ModName::from(m).to_string())
         .filter(|name| name.to_lowercase().starts_with(&current))
         .map(CompletionCandidate::new)
@@ -67,6 +142,7 @@ pub fn mod_index(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
 }
 
 pub fn enabled_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
+    let original = current;
     let Some(current) = current.to_str() else {
         return vec![];
     };
@@ -108,10 +184,12 @@ pub fn enabled_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
         .iter()
         .filter(|name| name.to_lowercase().starts_with(&current))
         .map(CompletionCandidate::new)
+        .chain(unmanaged_mods(original, Some(true)))
         .collect()
 }
 
 pub fn disabled_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
+    let original = current;
     let Some(current) = current.to_str() else {
         return vec![];
     };
@@ -153,5 +231,6 @@ pub fn disabled_mods(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
         .iter()
         .filter(|name| name.to_lowercase().starts_with(&current))
         .map(CompletionCandidate::new)
+        .chain(unmanaged_mods(original, Some(false)))
         .collect()
 }
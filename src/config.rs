@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Display;
 use std::fs;
 use std::path::PathBuf;
@@ -12,7 +12,9 @@ use figment::Figment;
 use figment::providers::{Env, Format, Serialized, Toml};
 use serde::{Deserialize, Serialize};
 
-use crate::IGNORED_DIRS;
+use tracing::warn;
+
+use crate::{IGNORED_DIRS, utils::parse_file_size};
 
 pub static DIRS: LazyLock<ProjectDirs> = LazyLock::new(|| {
     ProjectDirs::from("me", "greenboi", "Papa").expect("Unable to find base dirs")
@@ -25,9 +27,17 @@ pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
         .extract()
         .expect("Error reading configuration");
     cfg.config_path = Some(path);
+    cfg.parse_sizes();
+    // configs saved by older versions contain copies of the built-in templates, drop them so
+    // changes to the built-ins reach those users too
+    cfg.templates
+        .retain(|name, template| BUILTIN_TEMPLATES.get(name) != Some(template));
     cfg
 });
 
+static BUILTIN_TEMPLATES: LazyLock<BTreeMap<String, ProfileTemplate>> =
+    LazyLock::new(default_templates);
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     #[serde(skip)]
@@ -42,6 +52,80 @@ pub struct Config {
     install_type: InstallType,
     #[serde(default)]
     is_server: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup_keep: Option<usize>,
+    #[serde(
+        default = "default_trash_expiry",
+        skip_serializing_if = "is_default_trash_expiry"
+    )]
+    trash_expiry: u64,
+    /// User-defined templates, the built-in ones are added when they're read
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    templates: BTreeMap<String, ProfileTemplate>,
+    #[serde(
+        default = "default_repair_keep",
+        skip_serializing_if = "is_default_repair_keep"
+    )]
+    repair_keep: Vec<String>,
+    /// Largest the package cache can get before old packages are evicted, e.g. "2GB"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_max_size: Option<String>,
+    #[serde(skip)]
+    cache_max_bytes: Option<u64>,
+    /// Other mod managers' caches or profiles to take packages from before downloading them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cache_sources: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "DownloadConfig::is_default")]
+    download: DownloadConfig,
+}
+
+/// Settings for downloading packages and Northstar
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DownloadConfig {
+    /// How many more times to try a download after it fails
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Maximum download speed per second, e.g. "2MB"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_speed: Option<String>,
+    #[serde(skip)]
+    max_speed_bytes: Option<u64>,
+}
+
+impl DownloadConfig {
+    /// Maximum download speed in bytes per second, if one is set
+    pub fn max_speed(&self) -> Option<u64> {
+        self.max_speed_bytes
+    }
+
+    fn is_default(&self) -> bool {
+        self.retries == default_retries() && self.max_speed.is_none()
+    }
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_retries(),
+            max_speed: None,
+            max_speed_bytes: None,
+        }
+    }
+}
+
+/// A set of packages and settings to start a new profile with
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Packages to install, optionally with a version
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Enabled state of mods, by mod name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub enabled: BTreeMap<String, bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run_args: Vec<String>,
 }
 
 impl Config {
@@ -113,6 +197,64 @@ impl Config {
         self.ignore.remove(val.as_ref())
     }
 
+    pub fn backup_keep(&self) -> Option<usize> {
+        self.backup_keep
+    }
+
+    /// Number of days to keep items in the trash, or `None` to keep them forever
+    pub fn trash_expiry(&self) -> Option<u64> {
+        (self.trash_expiry > 0).then_some(self.trash_expiry)
+    }
+
+    pub fn template(&self, name: &str) -> Option<&ProfileTemplate> {
+        self.templates
+            .get(name)
+            .or_else(|| BUILTIN_TEMPLATES.get(name))
+    }
+
+    /// Every template by name, user-defined ones taking the place of built-in ones
+    pub fn templates(&self) -> BTreeMap<&str, &ProfileTemplate> {
+        BUILTIN_TEMPLATES
+            .iter()
+            .chain(&self.templates)
+            .map(|(name, template)| (name.as_str(), template))
+            .collect()
+    }
+
+    /// Patterns for files inside a package that 'papa repair --keep-config' leaves alone
+    pub fn repair_keep(&self) -> &[String] {
+        &self.repair_keep
+    }
+
+    /// Size limit of the package cache in bytes, if one is set
+    pub fn cache_max_size(&self) -> Option<u64> {
+        self.cache_max_bytes
+    }
+
+    pub fn cache_sources(&self) -> &[PathBuf] {
+        &self.cache_sources
+    }
+
+    pub fn download(&self) -> &DownloadConfig {
+        &self.download
+    }
+
+    /// Parse the human readable sizes in the config, warning about and ignoring invalid ones
+    fn parse_sizes(&mut self) {
+        self.cache_max_bytes = self.cache_max_size.as_ref().and_then(|raw| {
+            parse_file_size(raw)
+                .inspect_err(|e| warn!("Ignoring invalid cache_max_size '{raw}': {e}"))
+                .ok()
+        });
+
+        let download = &mut self.download;
+        download.max_speed_bytes = download.max_speed.as_ref().and_then(|raw| {
+            parse_file_size(raw)
+                .inspect_err(|e| warn!("Ignoring invalid download max_speed '{raw}': {e}"))
+                .ok()
+        });
+    }
+
     pub fn core_mods(&self) -> Option<PathBuf> {
         self.current_profile_dir().map(|dir| dir.join("mods"))
     }
@@ -135,6 +277,14 @@ impl Default for Config {
             current_profile: default_profile(),
             ignore: default_ignore_list(),
             install_type: InstallType::Other,
+            backup_keep: None,
+            trash_expiry: default_trash_expiry(),
+            templates: BTreeMap::new(),
+            repair_keep: default_repair_keep(),
+            cache_max_size: None,
+            cache_max_bytes: None,
+            cache_sources: vec![],
+            download: DownloadConfig::default(),
         }
     }
 }
@@ -147,6 +297,52 @@ pub fn default_ignore_list() -> HashSet<String> {
     IGNORED_DIRS.into_iter().map(String::from).collect()
 }
 
+pub fn default_trash_expiry() -> u64 {
+    14
+}
+
+pub fn default_repair_keep() -> Vec<String> {
+    vec!["**/*.cfg".into(), "**/*.ini".into(), "**/config/**".into()]
+}
+
+pub fn default_retries() -> u32 {
+    3
+}
+
+fn is_default_trash_expiry(days: &u64) -> bool {
+    *days == default_trash_expiry()
+}
+
+fn is_default_repair_keep(patterns: &Vec<String>) -> bool {
+    *patterns == default_repair_keep()
+}
+
+pub fn default_templates() -> BTreeMap<String, ProfileTemplate> {
+    BTreeMap::from([
+        (
+            "vanilla+".into(),
+            ProfileTemplate {
+                description: Some("Client-side mods only, for playing on vanilla servers".into()),
+                packages: vec!["NanohmProtogen.VanillaPlus".into()],
+                enabled: BTreeMap::from([
+                    ("Northstar.Custom".into(), false),
+                    ("Northstar.CustomServers".into(), false),
+                ]),
+                run_args: vec![],
+            },
+        ),
+        (
+            "server".into(),
+            ProfileTemplate {
+                description: Some("Dedicated server".into()),
+                packages: vec![],
+                enabled: BTreeMap::new(),
+                run_args: vec!["-dedicated".into()],
+            },
+        ),
+    ])
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum InstallType {
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+/// A single value that differs between two states of a profile
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Change<T> {
+    pub name: String,
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+impl<T> Change<T> {
+    /// Create a change only if `before` and `after` are different
+    pub fn compare(name: impl Into<String>, before: Option<T>, after: Option<T>) -> Option<Self>
+    where
+        T: PartialEq,
+    {
+        (before != after).then(|| Self {
+            name: name.into(),
+            before,
+            after,
+        })
+    }
+
+    /// The change that would undo this one
+    pub fn reverse(&self) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            name: self.name.clone(),
+            before: self.after.clone(),
+            after: self.before.clone(),
+        }
+    }
+}
+
+/// Compare two maps, returning a change for every key whose value differs
+pub(crate) fn diff_maps<T: PartialEq>(
+    mut before: BTreeMap<String, T>,
+    mut after: BTreeMap<String, T>,
+) -> Vec<Change<T>> {
+    let keys: BTreeSet<String> = before.keys().chain(after.keys()).cloned().collect();
+
+    keys.into_iter()
+        .filter_map(|name| {
+            let (b, a) = (before.remove(&name), after.remove(&name));
+            Change::compare(name, b, a)
+        })
+        .collect()
+}
+
+/// Print a change as `~ name old -> new`, `+ name new` or `- name old`
+pub(crate) fn print_change<T>(change: &Change<T>, indent: usize, fmt: impl Fn(&T) -> String) {
+    let pad = " ".repeat(indent);
+    match (&change.before, &change.after) {
+        (Some(a), Some(b)) => println!(
+            "{pad}{} {} {} -> {}",
+            "~".bright_yellow(),
+            change.name.bright_cyan(),
+            fmt(a).bright_red(),
+            fmt(b).bright_green()
+        ),
+        (Some(a), None) => println!(
+            "{pad}{} {} {}",
+            "-".bright_red(),
+            change.name.bright_cyan(),
+            fmt(a)
+        ),
+        (None, Some(b)) => println!(
+            "{pad}{} {} {}",
+            "+".bright_green(),
+            change.name.bright_cyan(),
+            fmt(b)
+        ),
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_and_reverse() {
+        let before = BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+        let after = BTreeMap::from([("b".to_string(), 3), ("c".to_string(), 4)]);
+
+        let changes = diff_maps(before, after);
+        assert_eq!(
+            changes,
+            [
+                Change {
+                    name: "a".into(),
+                    before: Some(1),
+                    after: None
+                },
+                Change {
+                    name: "b".into(),
+                    before: Some(2),
+                    after: Some(3)
+                },
+                Change {
+                    name: "c".into(),
+                    before: None,
+                    after: Some(4)
+                },
+            ]
+        );
+        assert_eq!(changes[1].reverse().after, Some(2));
+        assert!(Change::compare("same", Some(1), Some(1)).is_none());
+    }
+}
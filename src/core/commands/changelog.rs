@@ -0,0 +1,252 @@
+use std::{
+    fs::File,
+    ops::{Bound, RangeBounds},
+    sync::LazyLock,
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use regex::Regex;
+use semver::Version;
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    model::{Cache, ModName, PackageIndex},
+    traits::Index,
+    utils::{
+        ExternalPackage, download_package, ensure_dir, find_external, find_packages,
+        import_external_zip, read_zip_text,
+    },
+};
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[0-9]+\.[0-9]+\.[0-9]+").expect("version regex"));
+
+pub type VersionRange = (Bound<Version>, Bound<Version>);
+
+pub fn changelog(name: ModName, range: Option<String>, no_cache: bool) -> Result<()> {
+    let (from, to) = match range {
+        Some(range) => parse_range(&range)?,
+        None => (Bound::Unbounded, Bound::Unbounded),
+    };
+
+    // default to the changes since the installed version
+    let from = match from {
+        Bound::Unbounded => installed_version(&name)?.map_or(Bound::Unbounded, Bound::Excluded),
+        from => from,
+    };
+
+    let index = PackageIndex::fetch()?;
+    let Some(m) = index.get_item(&name) else {
+        println!("Couldn't find package {}", name.bright_cyan());
+        return Err(anyhow!("Package not found"));
+    };
+
+    let target = match &to {
+        Bound::Included(v) | Bound::Excluded(v) => v.to_string(),
+        Bound::Unbounded => m.latest.clone(),
+    };
+    let Some(version) = m.get_version(target.clone()) else {
+        println!(
+            "Package {} has no version {}",
+            name.bright_cyan(),
+            target.bright_cyan()
+        );
+        return Err(anyhow!("Version not found"));
+    };
+
+    let target_name = ModName::new(&m.author, &m.name, target.parse().ok());
+    let file = cached_or_download(&target_name, version, no_cache)?;
+
+    print_changelog(&target_name, file, &(from, to))
+}
+
+/// Open a package's zip from the cache, downloading it first if needed
+pub(crate) fn cached_or_download(
+    name: &ModName,
+    version: &thermite::model::ModVersion,
+    no_cache: bool,
+) -> Result<File> {
+    if !no_cache && let Some(file) = cached(name, version)? {
+        return Ok(file);
+    }
+
+    download_package(&Cache::from_dir(DIRS.cache_dir())?, name, version)
+}
+
+/// Open a package's zip if it's in the cache or one of the cache sources, without downloading it
+pub(crate) fn cached(
+    name: &ModName,
+    version: &thermite::model::ModVersion,
+) -> Result<Option<File>> {
+    ensure_dir(DIRS.cache_dir())?;
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+    if let Some(path) = cache.get(name) {
+        debug!("Using cached zip for {name}");
+        return Ok(Some(File::open(path)?));
+    }
+    if let Some(ExternalPackage::Zip(src)) = find_external(name, version) {
+        return Ok(Some(File::open(import_external_zip(&cache, name, &src)?)?));
+    }
+
+    Ok(None)
+}
+
+/// Print the parts of a package's changelog that fall within `range`
+pub(crate) fn print_changelog(name: &ModName, file: File, range: &VersionRange) -> Result<()> {
+    let Some(text) = read_zip_text(file, "CHANGELOG.md")? else {
+        println!("{} doesn't include a changelog", name.bright_cyan());
+        return Ok(());
+    };
+
+    match filter_sections(&text, range) {
+        Some(sections) => {
+            for line in sections.lines() {
+                if line.starts_with('#') {
+                    println!("{}", line.bright_cyan().bold());
+                } else {
+                    println!("{line}");
+                }
+            }
+        }
+        None => println!(
+            "The changelog for {} has no entries for these versions",
+            name.bright_cyan()
+        ),
+    }
+
+    Ok(())
+}
+
+fn installed_version(name: &ModName) -> Result<Option<Version>> {
+    let Ok(dir) = CONFIG.install_dir() else {
+        return Ok(None);
+    };
+
+    Ok(find_packages(dir)?
+        .into_iter()
+        .find(|(m, _)| {
+            m.author.to_lowercase() == name.author.to_lowercase()
+                && m.name.to_lowercase() == name.name.to_lowercase()
+        })
+        .and_then(|(m, _)| m.version))
+}
+
+/// Parse `FROM..TO`, where either side can be left out, or a single version
+fn parse_range(input: &str) -> Result<VersionRange> {
+    let parse = |v: &str| -> Result<Option<Version>> {
+        let v = v.trim().trim_start_matches('v');
+        if v.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(v.parse()?))
+        }
+    };
+
+    Ok(match input.split_once("..") {
+        Some((from, to)) => (
+            parse(from)?.map_or(Bound::Unbounded, Bound::Excluded),
+            parse(to)?.map_or(Bound::Unbounded, Bound::Included),
+        ),
+        None => {
+            let v = parse(input)?.ok_or_else(|| anyhow!("Missing version"))?;
+            (Bound::Included(v.clone()), Bound::Included(v))
+        }
+    })
+}
+
+/// Keep only the sections of a changelog whose heading names a version in `range`
+///
+/// Changelogs without any version headings are returned whole. Returns `None` if none of the
+/// version headings are in range.
+fn filter_sections(text: &str, range: &VersionRange) -> Option<String> {
+    let mut in_code = false;
+    let headings: Vec<(usize, usize, Option<Version>)> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            if in_code {
+                return None;
+            }
+
+            let level = line.chars().take_while(|c| *c == '#').count();
+            if level == 0 || !line[level..].starts_with(' ') {
+                return None;
+            }
+
+            let version = VERSION_RE.find(line).and_then(|m| m.as_str().parse().ok());
+            Some((i, level, version))
+        })
+        .collect();
+
+    let Some(level) = headings
+        .iter()
+        .filter(|(_, _, v)| v.is_some())
+        .map(|(_, level, _)| *level)
+        .min()
+    else {
+        return Some(text.to_string());
+    };
+
+    let sections: Vec<(usize, Option<Version>)> = headings
+        .into_iter()
+        .filter(|(_, l, _)| *l <= level)
+        .map(|(i, _, v)| (i, v))
+        .collect();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out = vec![];
+    for (n, (start, version)) in sections.iter().enumerate() {
+        let Some(version) = version else {
+            continue;
+        };
+        if !range.contains(version) {
+            continue;
+        }
+
+        let end = sections.get(n + 1).map_or(lines.len(), |(i, _)| *i);
+        out.push(lines[*start..end].join("\n").trim_end().to_string());
+    }
+
+    (!out.is_empty()).then(|| out.join("\n\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CHANGELOG: &str = "# Changelog\n\n## 1.2.0\n- new thing\n\n## 1.1.0\n- fix\n\n```\n## 9.9.9\n```\n\n## 1.0.0\n- first\n";
+
+    #[test]
+    fn filters_sections_in_range() {
+        let range = parse_range("1.0.0..1.2.0").unwrap();
+        let out = filter_sections(CHANGELOG, &range).unwrap();
+
+        assert!(out.contains("## 1.2.0"));
+        assert!(
+            out.contains("## 9.9.9"),
+            "code blocks stay in their section"
+        );
+        assert!(!out.contains("## 1.0.0"));
+        assert!(!out.contains("# Changelog"));
+    }
+
+    #[test]
+    fn keeps_unversioned_changelogs() {
+        let text = "# Changes\n- stuff\n";
+        let range = parse_range("1.0.0").unwrap();
+
+        assert_eq!(filter_sections(text, &range).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn nothing_in_range() {
+        let range = parse_range("2.0.0..").unwrap();
+
+        assert!(filter_sections(CHANGELOG, &range).is_none());
+    }
+}
@@ -3,13 +3,19 @@ use std::collections::BTreeSet;
 use anyhow::Result;
 use owo_colors::OwoColorize;
 use thermite::{
+    CORE_MODS,
     model::{EnabledMods, InstalledMod},
     prelude::find_mods,
-    CORE_MODS,
 };
 use tracing::debug;
 
-use crate::{config::CONFIG, get_answer, model::ModName, traits::Answer, utils::find_enabled_mods};
+use crate::{
+    config::CONFIG,
+    get_answer,
+    model::ModName,
+    traits::Answer,
+    utils::{did_you_mean, find_enabled_mods, find_unmanaged, submod_states, suggest},
+};
 
 pub fn disable(mods: BTreeSet<String>, all: bool, force: bool) -> Result<()> {
     for m in mods.iter() {
@@ -60,6 +66,23 @@ pub fn disable(mods: BTreeSet<String>, all: bool, force: bool) -> Result<()> {
         })
         .collect::<Vec<(String, InstalledMod)>>();
 
+    // mods in the legacy 'mods' folder can only be matched by their own name
+    let unmanaged = find_unmanaged(&dir)?
+        .into_iter()
+        .filter_map(|(_, json)| {
+            if all {
+                if CORE_MODS.contains(&json.name.to_lowercase().as_str()) && !force {
+                    debug!("Skipping core mod {} when disabling all", json.name);
+                    return None;
+                }
+                return Some((json.name.clone(), json.name));
+            }
+            mods.iter()
+                .find(|m| m.to_lowercase() == json.name.to_lowercase())
+                .map(|m| (m.clone(), json.name))
+        })
+        .collect::<Vec<_>>();
+
     let mut enabled_mods = match find_enabled_mods(&dir) {
         Some(mods) => mods,
         None => EnabledMods::default_with_path(dir.join("..").join("enabledmods.json")),
@@ -73,9 +96,29 @@ pub fn disable(mods: BTreeSet<String>, all: bool, force: bool) -> Result<()> {
         println!("Disabled {}", i.mod_json.name.bright_red());
         acted.insert(idx.clone());
     }
+    for (idx, name) in unmanaged {
+        enabled_mods.set(&name, false);
+        println!("Disabled {} {}", name.bright_red(), "(unmanaged)".dimmed());
+        acted.insert(idx);
+    }
 
-    for m in mods.difference(&acted) {
-        println!("Couldn't find {}", m.bright_cyan());
+    let diff = mods.difference(&acted).collect::<Vec<_>>();
+    if !diff.is_empty() {
+        let states = submod_states(dir.join(".."))?;
+        let enabled = states
+            .iter()
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| name.as_str())
+            // never guess our way past the core mod warning
+            .filter(|name| force || !CORE_MODS.contains(&name.to_lowercase().as_str()))
+            .collect::<Vec<_>>();
+        for m in diff {
+            println!("Couldn't find {}", m.bright_cyan());
+            if let Some(name) = did_you_mean(&suggest(m, enabled.iter().copied()), false)? {
+                enabled_mods.set(name, false);
+                println!("Disabled {}", name.bright_red());
+            }
+        }
     }
 
     // ensure the file gets saved, drop seems to be failing silently
@@ -0,0 +1,362 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use thermite::{
+    CORE_MODS,
+    core::manage::install_northstar_profile,
+    model::{EnabledMods, Manifest, ModJSON},
+    prelude::*,
+};
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, default_profile},
+    core::trash::trash,
+    get_answer,
+    model::ModName,
+    traits::Answer,
+    utils::{
+        break_links, cached_northstar, download_northstar, find_enabled_mods, find_package_roots,
+        find_submods, northstar_version,
+    },
+};
+
+use super::install;
+
+const GAME_FILES: [&str; 2] = ["NorthstarLauncher.exe", "Northstar.dll"];
+
+enum Problem {
+    Uninitialized,
+    MissingGameFile(PathBuf),
+    BrokenPackage { path: PathBuf, reason: String },
+    MissingDependency { package: ModName, dep: ModName },
+    StaleEntry(String),
+    MissingCoreMod(String),
+    DisabledCoreMod(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uninitialized => write!(f, "The config hasn't been initialized"),
+            Self::MissingGameFile(path) => write!(f, "Missing game file '{}'", path.display()),
+            Self::BrokenPackage { path, reason } => write!(
+                f,
+                "Package '{}' is broken: {reason}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            Self::MissingDependency { package, dep } => {
+                write!(
+                    f,
+                    "Package '{package}' depends on '{dep}', which isn't installed"
+                )
+            }
+            Self::StaleEntry(name) => {
+                write!(f, "'{name}' is in enabledmods.json but isn't installed")
+            }
+            Self::MissingCoreMod(name) => write!(f, "Core mod '{name}' is missing"),
+            Self::DisabledCoreMod(name) => write!(f, "Core mod '{name}' is disabled"),
+        }
+    }
+}
+
+pub fn doctor(fix: bool, yes: bool, no_cache: bool) -> Result<()> {
+    let Some(profile) = CONFIG.current_profile_dir() else {
+        print_problems(&[Problem::Uninitialized]);
+        println!("Run '{}' to set things up", "papa ns init".bright_cyan());
+        return Err(anyhow!("Found 1 problem"));
+    };
+
+    println!(
+        "Checking profile {}...",
+        CONFIG.current_profile().bright_purple().bold()
+    );
+
+    let mut problems = vec![];
+    problems.append(&mut check_game_files());
+    problems.append(&mut check_packages()?);
+    problems.append(&mut check_enabled_mods(&profile)?);
+
+    if problems.is_empty() {
+        println!("No problems found!");
+        return Ok(());
+    }
+
+    print_problems(&problems);
+
+    if !fix {
+        println!(
+            "\nRun '{}' to try to fix them",
+            "papa doctor --fix".bright_cyan()
+        );
+        return Err(anyhow!(
+            "Found {} {}",
+            problems.len(),
+            plural(problems.len())
+        ));
+    }
+
+    println!();
+    let answer = get_answer!(yes, "Attempt to fix these problems? [Y/n]: ")?;
+    if answer.is_no() {
+        return Err(anyhow!(
+            "Found {} {}",
+            problems.len(),
+            plural(problems.len())
+        ));
+    }
+
+    fix_problems(problems, &profile, yes, no_cache)?;
+
+    println!(
+        "Done! Run '{}' again to verify",
+        "papa doctor".bright_cyan()
+    );
+
+    Ok(())
+}
+
+fn print_problems(problems: &[Problem]) {
+    println!(
+        "\nFound {} {}:",
+        problems.len().bold(),
+        plural(problems.len())
+    );
+    for p in problems {
+        println!("  {} {p}", "x".bright_red());
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "problem" } else { "problems" }
+}
+
+fn check_game_files() -> Vec<Problem> {
+    let Some(game) = CONFIG.game_dir() else {
+        return vec![];
+    };
+
+    GAME_FILES
+        .into_iter()
+        .map(|f| game.join(f))
+        .filter(|path| !path.try_exists().unwrap_or(false))
+        .map(Problem::MissingGameFile)
+        .collect()
+}
+
+fn check_packages() -> Result<Vec<Problem>> {
+    let dir = CONFIG.install_dir()?;
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+
+    let mut problems = vec![];
+    let mut installed = BTreeSet::new();
+    let mut dependencies = vec![];
+    for root in find_package_roots(&dir)? {
+        let broken = |reason: String| Problem::BrokenPackage {
+            path: root.clone(),
+            reason,
+        };
+
+        let Ok(name) = ModName::try_from(root.as_path()) else {
+            problems.push(broken("unable to parse the package name".into()));
+            continue;
+        };
+
+        let manifest = match fs::read_to_string(root.join("manifest.json"))
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| Ok(serde_json::from_str::<Manifest>(&raw)?))
+        {
+            Ok(m) => m,
+            Err(e) => {
+                problems.push(broken(format!("invalid manifest.json ({e})")));
+                continue;
+            }
+        };
+
+        if let Some(reason) = check_submods(&root.join("mods"))? {
+            problems.push(broken(reason));
+        }
+
+        installed.insert((name.author.to_lowercase(), name.name.to_lowercase()));
+        for dep in manifest.dependencies {
+            match ModName::try_from(dep.as_str()) {
+                Ok(dep) => dependencies.push((name.clone(), dep)),
+                Err(e) => debug!("Skipping invalid dependency '{dep}': {e}"),
+            }
+        }
+    }
+
+    for (package, dep) in dependencies {
+        if dep.author.to_lowercase() == "northstar" {
+            continue;
+        }
+
+        if !installed.contains(&(dep.author.to_lowercase(), dep.name.to_lowercase())) {
+            problems.push(Problem::MissingDependency { package, dep });
+        }
+    }
+
+    Ok(problems)
+}
+
+fn check_submods(mods: &Path) -> Result<Option<String>> {
+    if !mods.try_exists()? {
+        return Ok(None);
+    }
+
+    for entry in fs::read_dir(mods)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name();
+        let Ok(raw) = fs::read_to_string(entry.path().join("mod.json")) else {
+            return Ok(Some(format!(
+                "submod '{}' is missing mod.json",
+                name.to_string_lossy()
+            )));
+        };
+
+        if let Err(e) = serde_json::from_str::<ModJSON>(&raw) {
+            return Ok(Some(format!(
+                "submod '{}' has an invalid mod.json ({e})",
+                name.to_string_lossy()
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+fn check_enabled_mods(profile: &Path) -> Result<Vec<Problem>> {
+    let mut problems = vec![];
+
+    let core = find_submods(profile.join("mods"))?;
+    let enabled = find_enabled_mods(profile);
+    for name in CORE_MODS {
+        // enabledmods.json is case-sensitive, so use the name the mod gives itself
+        match core
+            .iter()
+            .find(|(_, json)| json.name.eq_ignore_ascii_case(name))
+        {
+            None => problems.push(Problem::MissingCoreMod(name.into())),
+            Some((_, json)) => {
+                if let Some(em) = enabled.as_ref()
+                    && !em.is_enabled(&json.name)
+                {
+                    problems.push(Problem::DisabledCoreMod(json.name.clone()));
+                }
+            }
+        }
+    }
+
+    let Some(enabled) = enabled else {
+        return Ok(problems);
+    };
+
+    let mut known: BTreeSet<String> = core.into_iter().map(|(_, json)| json.name).collect();
+    let dir = CONFIG.install_dir()?;
+    if dir.try_exists()? {
+        known.extend(find_mods(&dir)?.into_iter().map(|m| m.mod_json.name));
+    }
+
+    for name in enabled.mods.keys() {
+        if !known.contains(name) {
+            problems.push(Problem::StaleEntry(name.clone()));
+        }
+    }
+
+    Ok(problems)
+}
+
+fn fix_problems(problems: Vec<Problem>, profile: &Path, yes: bool, no_cache: bool) -> Result<()> {
+    let mut reinstall = BTreeSet::new();
+    let mut missing = BTreeSet::new();
+    let mut stale = vec![];
+    let mut disabled = vec![];
+    let mut game_files = false;
+    let mut core_mods = false;
+
+    for p in problems {
+        match p {
+            Problem::Uninitialized => {}
+            Problem::MissingGameFile(_) => game_files = true,
+            Problem::MissingCoreMod(_) => core_mods = true,
+            Problem::DisabledCoreMod(name) => disabled.push(name),
+            Problem::StaleEntry(name) => stale.push(name),
+            Problem::MissingDependency { dep, .. } => {
+                missing.insert(ModName::new(dep.author, dep.name, None));
+            }
+            Problem::BrokenPackage { path, .. } => {
+                let Ok(name) = ModName::try_from(path.as_path()) else {
+                    println!(
+                        "Can't repair '{}', remove it manually",
+                        path.display().bright_red()
+                    );
+                    continue;
+                };
+                println!("Removing broken package {}", name.bright_cyan());
+                trash(&path, format!("doctor --fix {name}"))?;
+                reinstall.insert(name);
+            }
+        }
+    }
+
+    if game_files || core_mods {
+        let version = northstar_version(profile);
+        let open = || -> Result<fs::File> {
+            if !no_cache && let Some(file) = cached_northstar(version.as_ref())? {
+                Ok(file)
+            } else {
+                download_northstar(version.clone())
+            }
+        };
+
+        if game_files && let Some(game) = CONFIG.game_dir() {
+            println!("Reinstalling Northstar to {}", game.display().bright_cyan());
+            break_links(game.join(default_profile()).join("mods"))?;
+            install_northstar(open()?, game)?;
+        }
+
+        if core_mods {
+            println!("Reinstalling Northstar core mods");
+            break_links(profile.join("mods"))?;
+            install_northstar_profile(open()?, profile)?;
+        }
+    }
+
+    if !stale.is_empty() || !disabled.is_empty() {
+        let mut enabled = match find_enabled_mods(profile) {
+            Some(mods) => mods,
+            None => EnabledMods::default_with_path(profile.join("enabledmods.json")),
+        };
+
+        for name in stale {
+            println!("Removing stale entry {}", name.bright_cyan());
+            enabled.mods.remove(&name);
+        }
+
+        for name in disabled {
+            println!("Enabling {}", name.bright_green());
+            enabled.set(&name, true);
+        }
+
+        enabled.save()?;
+    }
+
+    let to_install: Vec<ModName> = reinstall.into_iter().chain(missing).collect();
+    if !to_install.is_empty() {
+        install(to_install, yes, true, no_cache)?;
+    }
+
+    Ok(())
+}
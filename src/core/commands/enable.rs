@@ -4,7 +4,11 @@ use anyhow::Result;
 use owo_colors::OwoColorize;
 use tracing::debug;
 
-use crate::{config::CONFIG, model::ModName, utils::find_enabled_mods};
+use crate::{
+    config::CONFIG,
+    model::ModName,
+    utils::{did_you_mean, find_enabled_mods, find_unmanaged, submod_states, suggest},
+};
 use thermite::{
     model::{EnabledMods, InstalledMod},
     prelude::find_mods,
@@ -34,6 +38,19 @@ pub fn enable(mods: BTreeSet<String>, all: bool) -> Result<()> {
         })
         .collect::<Vec<(String, InstalledMod)>>();
 
+    // mods in the legacy 'mods' folder can only be matched by their own name
+    let unmanaged = find_unmanaged(&dir)?
+        .into_iter()
+        .filter_map(|(_, json)| {
+            if all {
+                return Some((json.name.clone(), json.name));
+            }
+            mods.iter()
+                .find(|m| m.to_lowercase() == json.name.to_lowercase())
+                .map(|m| (m.clone(), json.name))
+        })
+        .collect::<Vec<_>>();
+
     let mut enabled_mods = match find_enabled_mods(dir.join("..")) {
         Some(mods) => mods,
         None => EnabledMods::default_with_path(dir.join("..").join("enabledmods.json")),
@@ -47,10 +64,27 @@ pub fn enable(mods: BTreeSet<String>, all: bool) -> Result<()> {
         println!("Enabled {}", i.mod_json.name.bright_green());
         acted.insert(idx.clone());
     }
+    for (idx, name) in unmanaged {
+        enabled_mods.set(&name, true);
+        println!("Enabled {} {}", name.bright_green(), "(unmanaged)".dimmed());
+        acted.insert(idx);
+    }
 
-    let diff = mods.difference(&acted);
-    for m in diff {
-        println!("Couldn't find {}", m.bright_cyan());
+    let diff = mods.difference(&acted).collect::<Vec<_>>();
+    if !diff.is_empty() {
+        let states = submod_states(dir.join(".."))?;
+        let disabled = states
+            .iter()
+            .filter(|(_, enabled)| !**enabled)
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        for m in diff {
+            println!("Couldn't find {}", m.bright_cyan());
+            if let Some(name) = did_you_mean(&suggest(m, disabled.iter().copied()), false)? {
+                enabled_mods.set(name, true);
+                println!("Enabled {}", name.bright_green());
+            }
+        }
     }
 
     enabled_mods.save()?;
@@ -0,0 +1,375 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thermite::model::EnabledMods;
+use time::{OffsetDateTime, macros::format_description};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{CONFIG, DIRS},
+    core::{profile::install_exact, trash::trash},
+    get_answer,
+    model::ModName,
+    traits::Answer,
+    utils::{
+        ensure_dir, find_enabled_mods, find_packages, init_msg, northstar_version, submod_states,
+    },
+};
+
+use super::change::{Change, diff_maps, print_change};
+
+/// The state of a profile that a command might change
+pub struct Snapshot {
+    profile: String,
+    northstar: Option<String>,
+    packages: BTreeMap<String, String>,
+    enabled: BTreeMap<String, bool>,
+    /// Whether to record what changed, or only that the command was run
+    track: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryEntry {
+    id: usize,
+    #[serde(with = "time::serde::rfc3339")]
+    time: OffsetDateTime,
+    command: String,
+    profile: String,
+    /// The entry this one reverted, if it was made by 'papa undo'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    undoes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    northstar: Option<Change<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    packages: Vec<Change<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    enabled: Vec<Change<bool>>,
+}
+
+impl HistoryEntry {
+    /// Whether the command didn't change anything that can be undone
+    fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.enabled.is_empty() && self.northstar.is_none()
+    }
+}
+
+fn history_file() -> PathBuf {
+    DIRS.data_dir().join("history.jsonl")
+}
+
+/// Capture the state of a profile before running a command that changes it
+pub fn snapshot(profile: &str) -> Option<Snapshot> {
+    let dir = CONFIG.game_dir()?.join(profile);
+
+    let packages = match find_packages(dir.join("packages")) {
+        Ok(packages) => packages
+            .into_iter()
+            .map(|(name, _)| {
+                let version = name
+                    .version
+                    .as_ref()
+                    .map(|v| v.to_string())
+                    .unwrap_or_default();
+                (
+                    ModName::new(name.author, name.name, None).to_string(),
+                    version,
+                )
+            })
+            .collect(),
+        Err(e) => {
+            debug!("Unable to read packages for history: {e}");
+            BTreeMap::new()
+        }
+    };
+
+    Some(Snapshot {
+        profile: profile.to_string(),
+        northstar: northstar_version(&dir).map(|v| v.to_string()),
+        packages,
+        enabled: submod_states(&dir).unwrap_or_default(),
+        track: true,
+    })
+}
+
+/// Record a command against a profile without tracking any changes to its mods
+pub fn untracked(profile: &str) -> Snapshot {
+    Snapshot {
+        profile: profile.to_string(),
+        northstar: None,
+        packages: BTreeMap::new(),
+        enabled: BTreeMap::new(),
+        track: false,
+    }
+}
+
+/// Add a command to the history, along with what it changed since `before` was taken
+pub fn record(command: impl Into<String>, before: Snapshot) -> Result<usize> {
+    record_entry(command.into(), before, None)
+}
+
+fn record_entry(command: String, before: Snapshot, undoes: Option<usize>) -> Result<usize> {
+    let after = if before.track {
+        snapshot(&before.profile).ok_or_else(init_msg)?
+    } else {
+        untracked(&before.profile)
+    };
+    let id = load()?.last().map_or(1, |e| e.id + 1);
+
+    let entry = HistoryEntry {
+        id,
+        time: OffsetDateTime::now_utc(),
+        command,
+        profile: before.profile,
+        undoes,
+        northstar: Change::compare("Northstar", before.northstar, after.northstar),
+        packages: diff_maps(before.packages, after.packages),
+        enabled: diff_maps(before.enabled, after.enabled),
+    };
+
+    ensure_dir(DIRS.data_dir())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_file())?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    debug!("Recorded history entry {id}");
+
+    Ok(id)
+}
+
+fn load() -> Result<Vec<HistoryEntry>> {
+    let raw = match fs::read_to_string(history_file()) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(raw
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| match serde_json::from_str(l) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Skipping unreadable history entry: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+/// Find the version a package had before the most recent command that changed it to `current`
+pub fn previous_version(
+    profile: &str,
+    package: &str,
+    current: &Version,
+) -> Result<Option<Version>> {
+    let package = package.to_lowercase();
+    let current = current.to_string();
+
+    Ok(load()?
+        .into_iter()
+        .rev()
+        .filter(|e| e.profile == profile)
+        .flat_map(|e| e.packages)
+        .find(|c| c.name.to_lowercase() == package && c.after.as_ref() == Some(&current))
+        .and_then(|c| c.before)
+        .and_then(|v| v.parse().ok()))
+}
+
+pub fn history(limit: usize, all: bool) -> Result<()> {
+    let entries = load()?;
+    if entries.is_empty() {
+        println!("No history recorded yet");
+        return Ok(());
+    }
+
+    let undone: BTreeSet<usize> = entries.iter().filter_map(|e| e.undoes).collect();
+    let fmt = format_description!("[year]-[month]-[day] [hour]:[minute]");
+    let count = if all { entries.len() } else { limit };
+    for entry in entries.iter().rev().take(count) {
+        print!(
+            "{} {} {} ({})",
+            format!("#{}", entry.id).bright_cyan(),
+            entry.time.format(fmt)?,
+            entry.command.bold(),
+            entry.profile.bright_purple()
+        );
+        if undone.contains(&entry.id) {
+            print!(" {}", "[undone]".bright_yellow());
+        }
+        println!();
+
+        print_changes(entry);
+    }
+
+    Ok(())
+}
+
+fn print_changes(entry: &HistoryEntry) {
+    let fmt_version = |v: &String| format!("v{v}");
+    let fmt_state = |s: &bool| if *s { "enabled" } else { "disabled" }.to_string();
+
+    if let Some(ns) = &entry.northstar {
+        print_change(ns, 4, fmt_version);
+    }
+    for change in &entry.packages {
+        print_change(change, 4, fmt_version);
+    }
+    for change in &entry.enabled {
+        print_change(change, 4, fmt_state);
+    }
+}
+
+pub fn undo(id: Option<usize>, yes: bool, no_cache: bool) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let entries = load()?;
+    let undone: BTreeSet<usize> = entries.iter().filter_map(|e| e.undoes).collect();
+    let entry = match id {
+        Some(id) => entries.into_iter().find(|e| e.id == id),
+        // walk back through the history instead of undoing the last undo, skipping commands
+        // like 'profile select' that didn't change any mods
+        None => entries
+            .into_iter()
+            .rev()
+            .find(|e| e.undoes.is_none() && !undone.contains(&e.id) && !e.is_empty()),
+    };
+    let Some(entry) = entry else {
+        println!("Nothing to undo");
+        return Ok(());
+    };
+
+    let prof = game.join(&entry.profile);
+    if !prof.try_exists()? {
+        println!(
+            "Profile {} no longer exists, check '{}' to get it back",
+            entry.profile.bright_cyan(),
+            "papa trash list".bright_cyan()
+        );
+        return Err(anyhow!("Profile not found"));
+    }
+
+    if entry.is_empty() {
+        println!(
+            "{} didn't change any mods, nothing to undo",
+            format!("#{}", entry.id).bright_cyan()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Undoing {} {}:",
+        format!("#{}", entry.id).bright_cyan(),
+        entry.command.bold()
+    );
+    // show the reverse of what the command did
+    let reverse = HistoryEntry {
+        packages: entry.packages.iter().map(Change::reverse).collect(),
+        enabled: entry.enabled.iter().map(Change::reverse).collect(),
+        northstar: entry.northstar.as_ref().map(Change::reverse),
+        ..entry
+    };
+    print_changes(&reverse);
+
+    let ans = get_answer!(yes)?;
+    if ans.is_no() {
+        return Ok(());
+    }
+
+    let Some(before) = snapshot(&reverse.profile) else {
+        return Err(init_msg());
+    };
+
+    let packages = prof.join("packages");
+    let to_install = reverse
+        .packages
+        .iter()
+        .filter_map(|change| {
+            let previous = change.after.as_ref()?;
+            match ModName::try_from(format!("{}@{previous}", change.name)) {
+                Ok(name) => Some(name),
+                Err(e) => {
+                    warn!("Unable to reinstall {}: {e}", change.name);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    // put the previous versions back before removing anything, so a failed download can't leave
+    // a package missing altogether
+    let before_install = find_packages(&packages)?;
+    ensure_dir(&packages)?;
+    install_exact(to_install, &prof, no_cache, false)?;
+    let installed = find_packages(&packages)?;
+
+    let find = |list: &[(ModName, PathBuf)], name: &str, version: &String| {
+        list.iter()
+            .find(|(n, _)| {
+                ModName::new(&n.author, &n.name, None).to_string() == name
+                    && n.version.as_ref().map(|v| v.to_string()).as_ref() == Some(version)
+            })
+            .map(|(_, path)| path.clone())
+    };
+    for change in &reverse.packages {
+        let Some(current) = &change.before else {
+            continue;
+        };
+        if let Some(previous) = &change.after
+            && find(&installed, &change.name, previous).is_none()
+        {
+            println!(
+                "Keeping {} v{current} since v{previous} couldn't be installed",
+                change.name.bright_cyan()
+            );
+            continue;
+        }
+
+        // remove whatever version the command left behind
+        if let Some(path) = find(&before_install, &change.name, current) {
+            trash(path, format!("undo {}", reverse.id))?;
+        }
+    }
+
+    // only revert what this command changed, later changes to other mods stay as they are
+    if !reverse.enabled.is_empty() {
+        let mut enabled_mods = match find_enabled_mods(&prof) {
+            Some(mods) => mods,
+            None => EnabledMods::default_with_path(prof.join("enabledmods.json")),
+        };
+        for change in &reverse.enabled {
+            if let Some(state) = change.after {
+                enabled_mods.set(&change.name, state);
+            } else {
+                enabled_mods.mods.remove(&change.name);
+            }
+        }
+        enabled_mods.save()?;
+    }
+
+    if let Some(Change {
+        after: Some(version),
+        ..
+    }) = &reverse.northstar
+    {
+        println!(
+            "Run '{}' to go back to Northstar v{version}",
+            format!("papa ns use {version} -p {}", reverse.profile).bright_cyan()
+        );
+    }
+
+    record_entry(format!("undo {}", reverse.id), before, Some(reverse.id))?;
+    println!("Undid {}", format!("#{}", reverse.id).bright_cyan());
+
+    Ok(())
+}
@@ -3,15 +3,15 @@ use thermite::model::ModVersion;
 use tracing::warn;
 
 use crate::get_answer;
-use crate::model::ModName;
+use crate::model::{ModName, PackageIndex};
 use crate::traits::{Answer, Index};
-use crate::utils::{download_and_install, to_file_size_string};
+use crate::utils::{did_you_mean, download_and_install, suggest, to_file_size_string};
 
 use owo_colors::OwoColorize;
 use thermite::prelude::*;
 
 pub fn install(mods: Vec<ModName>, assume_yes: bool, force: bool, no_cache: bool) -> Result<()> {
-    let remote_index = get_package_index()?;
+    let remote_index = PackageIndex::fetch()?;
     let mut valid: Vec<(ModName, &ModVersion)> = vec![];
     let mut should_fail = false;
     for mn in mods {
@@ -24,7 +24,24 @@ pub fn install(mods: Vec<ModName>, assume_yes: bool, force: bool, no_cache: bool
             should_fail = !force;
             continue;
         }
-        if let Some(m) = remote_index.get_item(&mn) {
+        let found = match remote_index.get_item(&mn) {
+            Some(m) => Some(m),
+            None => {
+                println!("Couldn't find package {}", mn.bright_cyan());
+                let names = remote_index
+                    .iter()
+                    .map(|m| format!("{}.{}", m.author, m.name))
+                    .collect::<Vec<_>>();
+                let suggestions = suggest(
+                    &format!("{}.{}", mn.author, mn.name),
+                    names.iter().map(String::as_str),
+                );
+                did_you_mean(&suggestions, assume_yes)?
+                    .and_then(|name| remote_index.get_item(&ModName::try_from(name).ok()?))
+            }
+        };
+
+        if let Some(m) = found {
             if let Some(version) = &mn.version {
                 let Some(mv) = m.get_version(version.to_string()) else {
                     println!(
@@ -35,7 +52,7 @@ pub fn install(mods: Vec<ModName>, assume_yes: bool, force: bool, no_cache: bool
                     should_fail = true;
                     continue;
                 };
-                valid.push((m.into(), mv));
+                valid.push((ModName::new(&m.author, &m.name, Some(version.clone())), mv));
             } else {
                 valid.push((
                     m.into(),
@@ -44,7 +61,6 @@ pub fn install(mods: Vec<ModName>, assume_yes: bool, force: bool, no_cache: bool
                 ));
             }
         } else {
-            println!("Couldn't find package {}", mn.bright_cyan());
             should_fail = true;
         }
     }
@@ -6,10 +6,14 @@ use std::{
 use anyhow::Result;
 use owo_colors::OwoColorize;
 use semver::Version;
-use thermite::{model::ModJSON, prelude::*};
+use thermite::{CORE_MODS, prelude::*};
 use tracing::{debug, error, trace};
 
-use crate::{config::CONFIG, model::ModName, utils::find_enabled_mods};
+use crate::{
+    config::CONFIG,
+    model::ModName,
+    utils::{find_enabled_mods, find_unmanaged, northstar_version},
+};
 
 pub fn list(global: bool, _all: bool) -> Result<()> {
     if global {
@@ -60,13 +64,24 @@ pub fn list(global: bool, _all: bool) -> Result<()> {
         }
     }
 
-    let nsversion: Option<Version> = CONFIG.core_mods().and_then(|dir| {
-        let modfile =
-            std::fs::read_to_string(dir.join("Northstar.Client").join("mod.json")).ok()?;
-        let json: ModJSON = serde_json::from_str(&modfile).ok()?;
+    let unmanaged: Vec<(String, String, bool)> = match find_unmanaged(CONFIG.install_dir()?) {
+        Ok(mods) => mods
+            .into_iter()
+            .filter(|(_, json)| !CORE_MODS.contains(&json.name.to_lowercase().as_str()))
+            .map(|(_, json)| {
+                let enabled = enabled_mods
+                    .as_ref()
+                    .is_none_or(|em| em.is_enabled(&json.name));
+                (json.name, json.version, enabled)
+            })
+            .collect(),
+        Err(e) => {
+            error!("Error finding unmanaged mods: {e}");
+            vec![]
+        }
+    };
 
-        json.version.parse().ok()
-    });
+    let nsversion: Option<Version> = CONFIG.current_profile_dir().and_then(northstar_version);
 
     if !std::io::stdout().is_terminal() {
         let out = std::io::stdout();
@@ -97,11 +112,13 @@ pub fn list(global: bool, _all: bool) -> Result<()> {
         println!("Northstar {}", format!("v{version}").bright_cyan().bold());
     }
     println!();
-    if grouped_mods.is_empty() && disabled.is_empty() {
+    if grouped_mods.is_empty() && disabled.is_empty() && unmanaged.is_empty() {
         println!("No mods installed");
         return Ok(());
     }
-    println!("Installed mods: ");
+    if !grouped_mods.is_empty() {
+        println!("Installed mods: ");
+    }
     for (group, names) in grouped_mods {
         if names.len() == 1 {
             println!("-  {}", group.bright_blue().bold());
@@ -122,5 +139,23 @@ pub fn list(global: bool, _all: bool) -> Result<()> {
             }
         }
     }
+
+    if !unmanaged.is_empty() {
+        println!(
+            "Unmanaged mods, run '{}' to turn them into packages: ",
+            "papa migrate".bright_cyan()
+        );
+        for (name, version, enabled) in unmanaged {
+            if enabled {
+                println!(
+                    "-  {} {}",
+                    name.bright_yellow().bold(),
+                    format!("v{version}").dimmed()
+                );
+            } else {
+                println!("-  {} {}", name.magenta().bold(), "(disabled)".dimmed());
+            }
+        }
+    }
     Ok(())
 }
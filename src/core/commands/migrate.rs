@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use thermite::{
+    CORE_MODS,
+    model::{Manifest, Mod, ModJSON, ModVersion},
+};
+use time::{OffsetDateTime, macros::format_description};
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    core::{profile::apply_states, trash::move_item},
+    get_answer,
+    model::{ModName, PackageIndex},
+    traits::{Answer, Index},
+    utils::{
+        download_and_install, ensure_dir, find_packages, find_submods, init_msg, submod_states,
+    },
+};
+
+/// A package that one or more legacy mod folders came from
+struct Migration<'a> {
+    name: ModName,
+    version: &'a ModVersion,
+    folders: Vec<(PathBuf, ModJSON)>,
+    installed: bool,
+}
+
+pub fn migrate(yes: bool, no_cache: bool) -> Result<()> {
+    let (Some(profile), Some(mods_dir)) = (CONFIG.current_profile_dir(), CONFIG.core_mods()) else {
+        return Err(init_msg());
+    };
+    let install_dir = CONFIG.install_dir()?;
+
+    let legacy: Vec<_> = find_submods(&mods_dir)?
+        .into_iter()
+        .filter(|(_, json)| !CORE_MODS.contains(&json.name.to_lowercase().as_str()))
+        .collect();
+    if legacy.is_empty() {
+        println!("No legacy mods found in '{}'", mods_dir.display());
+        return Ok(());
+    }
+
+    let index = PackageIndex::fetch()?;
+    let installed = find_packages(&install_dir)?;
+
+    let mut migrations: BTreeMap<ModName, Migration> = BTreeMap::new();
+    let mut unmatched = vec![];
+    for (path, json) in legacy {
+        let Some((m, version)) = find_package(&path, &json, &index) else {
+            unmatched.push((path, json));
+            continue;
+        };
+
+        let key = ModName::new(&m.author, &m.name, None);
+        let migration = migrations.entry(key).or_insert_with(|| Migration {
+            name: ModName::new(&m.author, &m.name, version.version.parse().ok()),
+            version,
+            folders: vec![],
+            installed: installed.iter().any(|(i, _)| {
+                i.author.eq_ignore_ascii_case(&m.author) && i.name.eq_ignore_ascii_case(&m.name)
+            }),
+        });
+        migration.folders.push((path, json));
+    }
+
+    if migrations.is_empty() {
+        println!(
+            "None of the mods in '{}' match a package:",
+            mods_dir.display()
+        );
+        print_unmatched(&unmatched);
+        return Ok(());
+    }
+
+    println!(
+        "Found legacy mods from {} packages:",
+        migrations.len().bold()
+    );
+    for m in migrations.values() {
+        for (path, json) in &m.folders {
+            print!("    {} -> {}", folder_name(path), m.name.bright_cyan());
+            if m.installed {
+                print!(" {}", "(already installed)".dimmed());
+            } else if json.version != m.version.version {
+                print!(
+                    " {}",
+                    format!("(v{} isn't available)", json.version).yellow()
+                );
+            }
+            println!();
+        }
+    }
+
+    let ans = get_answer!(
+        yes,
+        "Move these mods to '{}'? [Y/n]: ",
+        "packages".bright_cyan()
+    )?;
+    if ans.is_no() {
+        return Ok(());
+    }
+
+    let states = submod_states(&profile)?;
+
+    let to_install = migrations
+        .values()
+        .filter(|m| !m.installed)
+        .map(|m| (m.name.clone(), m.version))
+        .collect::<Vec<_>>();
+    if !to_install.is_empty() {
+        download_and_install(to_install, !no_cache, false)?;
+    }
+
+    // keep the old folders around in case the packages don't behave the same
+    let stamp = OffsetDateTime::now_utc().format(format_description!(
+        "[year][month][day]-[hour][minute][second]"
+    ))?;
+    let backup = DIRS
+        .data_dir()
+        .join("backups")
+        .join(format!("legacy-mods-{}-{stamp}", CONFIG.current_profile()));
+    ensure_dir(&backup)?;
+
+    let mut moved = 0;
+    let mut kept_states = BTreeMap::new();
+    for m in migrations.values() {
+        for (path, json) in &m.folders {
+            debug!("Moving '{}' to '{}'", path.display(), backup.display());
+            move_item(path, &backup.join(folder_name(path)))?;
+            moved += 1;
+            if let Some(state) = states.get(&json.name) {
+                kept_states.insert(json.name.clone(), *state);
+            }
+        }
+    }
+
+    apply_states(&profile, &kept_states)?;
+
+    println!(
+        "Migrated {} mods, the old folders were moved to '{}'",
+        moved.bold(),
+        backup.display().bright_cyan()
+    );
+    if !unmatched.is_empty() {
+        println!("These mods couldn't be matched to a package and were left in place:");
+        print_unmatched(&unmatched);
+    }
+
+    Ok(())
+}
+
+/// Find the package and version a legacy mod folder was installed from
+///
+/// Folders installed by older versions of papa still have their package manifest, otherwise the
+/// name in `mod.json` is matched against the package index.
+fn find_package<'a>(
+    path: &Path,
+    json: &ModJSON,
+    index: &'a PackageIndex,
+) -> Option<(&'a Mod, &'a ModVersion)> {
+    if let Ok(author) = fs::read_to_string(path.join("thunderstore_author.txt"))
+        && let Ok(raw) = fs::read_to_string(path.join("manifest.json"))
+        && let Ok(manifest) = serde_json::from_str::<Manifest>(&raw)
+        && let Some(m) = index.get_item(&ModName::new(author.trim(), &manifest.name, None))
+    {
+        debug!("Found manifest for '{}'", path.display());
+        let version = m
+            .get_version(&manifest.version_number)
+            .or_else(|| m.get_latest())?;
+        return Some((m, version));
+    }
+
+    let mut candidates: Vec<&Mod> = index
+        .iter()
+        .filter(|m| names_match(&json.name, &m.author, &m.name))
+        .collect();
+    if candidates.len() > 1 {
+        candidates.retain(|m| m.get_version(&json.version).is_some());
+    }
+    let [m] = candidates[..] else {
+        debug!(
+            "Found {} packages matching '{}'",
+            candidates.len(),
+            json.name
+        );
+        return None;
+    };
+
+    let version = m.get_version(&json.version).or_else(|| m.get_latest())?;
+    Some((m, version))
+}
+
+/// Check whether a name from `mod.json` refers to a package
+///
+/// Mod names usually look like `Author.Package`, but the punctuation and case rarely match the
+/// package name exactly.
+fn names_match(mod_name: &str, author: &str, package: &str) -> bool {
+    let package = normalize(package);
+    if let Some((a, p)) = mod_name.split_once('.')
+        && normalize(a) == normalize(author)
+        && normalize(p) == package
+    {
+        return true;
+    }
+
+    let mod_name = normalize(mod_name);
+    mod_name == package || mod_name == normalize(author) + &package
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn print_unmatched(unmatched: &[(PathBuf, ModJSON)]) {
+    for (path, json) in unmatched {
+        println!(
+            "    {} ({} v{})",
+            folder_name(path).bright_red(),
+            json.name,
+            json.version
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_mod_names() {
+        assert!(names_match(
+            "Fifty.ServerUtilities",
+            "Fifty",
+            "Server_Utilities"
+        ));
+        assert!(names_match("Server Utilities", "Fifty", "Server_Utilities"));
+        assert!(names_match(
+            "fifty-serverutilities",
+            "Fifty",
+            "Server_Utilities"
+        ));
+        assert!(!names_match("Other.ServerUtilities", "Fifty", "Other_Mod"));
+        assert!(!names_match("Fifty.Server", "Fifty", "Server_Utilities"));
+    }
+}
@@ -31,8 +31,31 @@ pub use export::export;
 mod env;
 pub use env::env;
 
+pub mod changelog;
+pub use changelog::changelog;
+
+mod readme;
+pub use readme::readme;
+
+mod rollback;
+pub use rollback::rollback;
+
+pub mod verify;
+pub use verify::{repair, verify};
+
+mod migrate;
+pub use migrate::migrate;
+
+mod doctor;
+pub use doctor::doctor;
+
+mod change;
+
 mod run;
 pub use run::RunOptions;
 pub use run::run;
 
+pub mod history;
+pub mod preset;
 pub mod profile;
+pub mod trash;
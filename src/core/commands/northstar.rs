@@ -1,16 +1,22 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::{self, OpenOptions};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::config::{DIRS, InstallType, SteamType};
-use crate::model::Cache;
+use crate::config::{DIRS, InstallType, SteamType, default_profile};
+use crate::core::trash::trash;
+use crate::model::{Cache, PackageIndex};
 use crate::traits::{Answer, Index};
-use crate::utils::{ensure_dir, init_msg};
+use crate::utils::{
+    break_links, cached_northstar, download, download_northstar, ensure_dir, init_msg,
+    northstar_version,
+};
 use crate::{NstarCommands, config::CONFIG, model::ModName};
 use crate::{get_answer, modfile};
 use anyhow::{Result, anyhow};
 use indicatif::{ProgressBar, ProgressStyle};
 use owo_colors::OwoColorize;
+use semver::Version;
 use steamlocate::SteamDir;
 use thermite::model::{Mod, ModJSON};
 use thermite::prelude::*;
@@ -18,13 +24,20 @@ use tracing::{debug, warn};
 
 use super::profile;
 
-pub fn northstar(commands: &NstarCommands) -> Result<()> {
+pub fn northstar(commands: &NstarCommands, global_no_cache: bool) -> Result<()> {
     match commands {
         NstarCommands::Init {
             force,
             path,
             no_cache,
-        } => init_ns(*force, path.as_ref(), *no_cache)?,
+        } => init_ns(*force, path.as_ref(), *no_cache || global_no_cache)?,
+        NstarCommands::Use {
+            version,
+            profile,
+            pin,
+            yes,
+        } => use_version(version, profile.as_deref(), *pin, *yes, global_no_cache)?,
+        NstarCommands::Versions { all } => list_versions(*all)?,
         NstarCommands::Update {} => {
             update_ns()?;
         }
@@ -74,7 +87,7 @@ fn init_ns(force: bool, path: Option<impl AsRef<Path>>, no_cache: bool) -> Resul
         return Ok(());
     }
 
-    let index = get_package_index()?;
+    let index = PackageIndex::fetch()?;
     let nsmod = index
         .get_item(&ModName::new("northstar", "Northstar", None))
         .ok_or(anyhow!("Couldn't find Northstar in the package index"))?;
@@ -89,10 +102,10 @@ fn init_ns(force: bool, path: Option<impl AsRef<Path>>, no_cache: bool) -> Resul
 
         OpenOptions::new().read(true).open(cached_ns)?
     } else {
-        let mut nsfile = modfile!(
-            DIRS.cache_dir()
-                .join(format!("{}.zip", ModName::from(nsmod)))
-        )?;
+        let path = DIRS
+            .cache_dir()
+            .join(format!("{}.zip", ModName::from(nsmod)));
+        let mut nsfile = modfile!(&path)?;
         let nsversion = nsmod.get_latest().expect("N* mod missing latest version");
 
         let pb = ProgressBar::new(nsversion.file_size)
@@ -104,12 +117,14 @@ fn init_ns(force: bool, path: Option<impl AsRef<Path>>, no_cache: bool) -> Resul
                 "Downloading Northstar version {}",
                 nsmod.latest.bold()
             ));
-        download_with_progress(&mut nsfile, &nsversion.url, |delta, _, _| {
-            pb.inc(delta);
-        })?;
+        download(&mut nsfile, &nsversion.url, &pb)?;
         pb.finish();
         println!();
 
+        if let Err(e) = cache.record(&path, Some(&nsversion.url)) {
+            debug!("Unable to update cache database: {e}");
+        }
+
         nsfile
     };
 
@@ -118,6 +133,8 @@ fn init_ns(force: bool, path: Option<impl AsRef<Path>>, no_cache: bool) -> Resul
         .with_prefix("Installing Northstar...")
         .with_message("");
     pb.enable_steady_tick(Duration::from_millis(50));
+    // profiles can share core files with each other, make sure we don't overwrite them all
+    break_links(titanfall_path.join(default_profile()).join("mods"))?;
     install_northstar(&nsfile, &titanfall_path)?;
     pb.finish_with_message("Done!");
 
@@ -131,7 +148,26 @@ fn init_ns(force: bool, path: Option<impl AsRef<Path>>, no_cache: bool) -> Resul
     Ok(())
 }
 
+/// Get the version of Northstar the current profile is pinned to, if any
+fn pinned_version() -> Result<Option<Version>> {
+    let Some(dir) = CONFIG.current_profile_dir() else {
+        return Ok(None);
+    };
+
+    Ok(profile::ProfileMeta::load(dir)?.northstar)
+}
+
 pub fn update_ns() -> Result<bool> {
+    if let Some(pinned) = pinned_version()? {
+        println!(
+            "Profile {} is pinned to Northstar {}, run '{}' to change it",
+            CONFIG.current_profile().bright_cyan(),
+            format!("v{pinned}").bright_cyan(),
+            "papa ns use <VERSION> --pin".bright_cyan()
+        );
+        return Ok(false);
+    }
+
     let Some((ns_client, remote_ns)) = update_check()? else {
         println!("Northstar is up to date!");
         return Ok(false);
@@ -167,12 +203,17 @@ pub fn update_ns() -> Result<bool> {
 }
 
 pub fn update_check() -> Result<Option<(PathBuf, Mod)>> {
+    if let Some(pinned) = pinned_version()? {
+        debug!("Current profile is pinned to Northstar v{pinned}, skipping update check");
+        return Ok(None);
+    }
+
     let ns_client_path = CONFIG
         .current_profile_dir()
         .ok_or_else(|| anyhow!("Unable to get current profile directory from config"))?
         .join("mods")
         .join("Northstar.Client");
-    let index = get_package_index()?;
+    let index = PackageIndex::fetch()?;
 
     if !ns_client_path.try_exists()? {
         debug!(
@@ -204,6 +245,150 @@ pub fn update_check() -> Result<Option<(PathBuf, Mod)>> {
     }
 }
 
+fn use_version(
+    version: &Version,
+    profile: Option<&str>,
+    pin: bool,
+    yes: bool,
+    no_cache: bool,
+) -> Result<()> {
+    let game = CONFIG.game_dir().ok_or_else(init_msg)?;
+    let name = profile.unwrap_or(CONFIG.current_profile());
+    let dir = game.join(name);
+    if !dir.try_exists()? {
+        println!("Profile {} doesn't exist", name.bright_cyan());
+        return Err(anyhow!("Profile not found"));
+    }
+
+    if northstar_version(&dir).as_ref() == Some(version) {
+        println!(
+            "Profile {} is already using Northstar {}",
+            name.bright_cyan(),
+            format!("v{version}").bright_cyan()
+        );
+        return Ok(());
+    }
+
+    let file = if !no_cache && let Some(file) = cached_northstar(Some(version))? {
+        println!(
+            "Using cached version of Northstar {}",
+            version.bright_cyan()
+        );
+        file
+    } else {
+        let ans = get_answer!(yes, "Download Northstar {}? [Y/n] ", version)?;
+        if ans.is_no() {
+            println!("Not downloading Northstar, aborting");
+            return Ok(());
+        }
+        download_northstar(Some(version.clone()))?
+    };
+
+    let pb = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::with_template("{prefix} {msg} {spinner}")?)
+        .with_prefix(format!("Installing Northstar v{version}..."))
+        .with_message("");
+    pb.enable_steady_tick(Duration::from_millis(50));
+    profile::replace_shared_northstar(file, &dir, &format!("ns use {version}"))?;
+    pb.finish_with_message("Done!");
+
+    let mut meta = profile::ProfileMeta::load(&dir)?;
+    if pin || meta.northstar.is_some() {
+        meta.northstar = Some(version.clone());
+        meta.save(&dir)?;
+    }
+
+    println!(
+        "Profile {} now uses Northstar {}",
+        name.bright_cyan(),
+        format!("v{version}").bright_cyan()
+    );
+
+    if let Some(launcher) = northstar_version(game.join(default_profile()))
+        && &launcher != version
+    {
+        println!(
+            "{} The Northstar launcher is shared by every profile and is currently v{launcher}. Mismatched versions may not work together.",
+            "!!".bright_yellow()
+        );
+    }
+
+    Ok(())
+}
+
+fn list_versions(all: bool) -> Result<()> {
+    const RECENT: usize = 10;
+
+    let nsname = ModName::new("northstar", "Northstar", None);
+    let index = PackageIndex::fetch()?;
+    let nsmod = index
+        .get_item(&nsname)
+        .ok_or_else(|| anyhow!("Unable to find Northstar in Thunderstore index"))?;
+
+    ensure_dir(DIRS.cache_dir())?;
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+    let cached: BTreeSet<Version> = cache
+        .get_all(&nsname)
+        .into_iter()
+        .filter_map(|(name, _)| name.version.clone())
+        .collect();
+
+    let mut used: BTreeMap<Version, Vec<String>> = BTreeMap::new();
+    if let Some(game) = CONFIG.game_dir() {
+        for dir in profile::find_profiles(game)? {
+            if let Some(version) = northstar_version(&dir) {
+                let name = dir.file_name().unwrap_or_default().to_string_lossy();
+                used.entry(version).or_default().push(name.to_string());
+            }
+        }
+    }
+
+    let mut versions: Vec<Version> = nsmod
+        .versions
+        .keys()
+        .filter_map(|v| v.parse().ok())
+        .chain(cached.iter().cloned())
+        .chain(used.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    versions.reverse();
+
+    println!("Northstar versions:");
+    for (i, version) in versions.iter().enumerate() {
+        let profiles = used.get(version);
+        if !all && i >= RECENT && profiles.is_none() && !cached.contains(version) {
+            continue;
+        }
+
+        let mut tags = vec![];
+        if version.to_string() == nsmod.latest {
+            tags.push("latest".bright_green().to_string());
+        }
+        if cached.contains(version) {
+            tags.push("cached".bright_yellow().to_string());
+        }
+
+        print!("  {:<10}", format!("v{version}").bright_cyan());
+        if !tags.is_empty() {
+            print!(" [{}]", tags.join(", "));
+        }
+        if let Some(profiles) = profiles {
+            print!(" used by {}", profiles.join(", ").bright_purple());
+        }
+        println!();
+    }
+
+    if !all && versions.len() > RECENT {
+        println!(
+            "\nRun '{}' to see every version",
+            "papa ns versions --all".bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
 const NSTAR_FILES: [&str; 8] = [
     "Northstar.dll",
     "LEGAL.txt",
@@ -235,14 +420,14 @@ fn reset(yes: bool) -> Result<()> {
     for file in NSTAR_FILES {
         let path = root.join(file);
         println!("Removing file {path:?}");
-        fs::remove_file(path)?;
+        trash(path, "ns reset")?;
     }
 
     let profiles = profile::find_profiles(root)?;
 
     for dir in profiles {
         println!("Removing profile {:?}", dir.file_name().expect("file name"));
-        fs::remove_dir_all(dir)?;
+        trash(dir, "ns reset")?;
     }
 
     println!(
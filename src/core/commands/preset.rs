@@ -0,0 +1,286 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{ErrorKind, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use clap::Subcommand;
+use clap_complete::ArgValueCompleter;
+use owo_colors::OwoColorize;
+use ron::ser::PrettyConfig;
+use thermite::model::EnabledMods;
+
+use crate::{
+    config::CONFIG,
+    core::trash::trash,
+    get_answer,
+    traits::Answer,
+    utils::{ensure_dir, find_enabled_mods, init_msg, submod_states},
+};
+
+type Preset = BTreeMap<String, bool>;
+
+#[derive(Subcommand)]
+pub enum PresetCommands {
+    ///Save the enabled state of every mod in the current profile
+    Save {
+        ///Name of the preset
+        name: String,
+        ///Overwrite an existing preset of the same name
+        #[arg(long, short)]
+        force: bool,
+    },
+    ///Enable and disable mods to match a preset
+    Apply {
+        #[clap(add = ArgValueCompleter::new(crate::completers::presets))]
+        name: String,
+    },
+    #[clap(alias("ls"))]
+    ///List the presets for the current profile
+    List,
+    ///Show how the current profile differs from a preset
+    Diff {
+        #[clap(add = ArgValueCompleter::new(crate::completers::presets))]
+        name: String,
+    },
+    #[clap(alias("rm"))]
+    ///Delete a preset
+    Delete {
+        #[clap(add = ArgValueCompleter::new(crate::completers::presets))]
+        name: String,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+pub fn handle(command: &PresetCommands) -> Result<()> {
+    let Some(profile) = CONFIG.current_profile_dir() else {
+        return Err(init_msg());
+    };
+
+    match command {
+        PresetCommands::Save { name, force } => save(&profile, name, *force),
+        PresetCommands::Apply { name } => apply(&profile, name),
+        PresetCommands::List => list(&profile),
+        PresetCommands::Diff { name } => diff(&profile, name),
+        PresetCommands::Delete { name, yes } => delete(&profile, name, *yes),
+    }
+}
+
+fn presets_dir(profile: impl AsRef<Path>) -> PathBuf {
+    profile.as_ref().join("papa-presets")
+}
+
+pub fn find_presets(profile: impl AsRef<Path>) -> Result<Vec<String>> {
+    let dir = presets_dir(profile);
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+
+    let mut presets = vec![];
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "ron")
+            && let Some(name) = path.file_stem()
+        {
+            presets.push(name.to_string_lossy().to_string());
+        }
+    }
+    presets.sort();
+
+    Ok(presets)
+}
+
+/// Path of a preset's file, refusing names that would point outside the presets folder
+fn preset_path(profile: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        println!(
+            "Invalid preset name {}, names can't contain path separators or start with '.'",
+            name.bright_red()
+        );
+        return Err(anyhow!("Invalid preset name"));
+    }
+
+    Ok(presets_dir(profile).join(format!("{name}.ron")))
+}
+
+fn load(profile: &Path, name: &str) -> Result<Preset> {
+    let raw = match fs::read_to_string(preset_path(profile, name)?) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("Preset {} doesn't exist", name.bright_cyan());
+            return Err(anyhow!("Preset not found"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(ron::from_str(&raw)?)
+}
+
+fn save(profile: &Path, name: &str, force: bool) -> Result<()> {
+    let path = preset_path(profile, name)?;
+    if !force && path.try_exists()? {
+        println!(
+            "Preset {} already exists, use {} to overwrite it",
+            name.bright_cyan(),
+            "--force".bright_cyan()
+        );
+        return Err(anyhow!("Preset already exists"));
+    }
+
+    let states = submod_states(profile)?;
+    ensure_dir(presets_dir(profile))?;
+    fs::write(
+        path,
+        ron::ser::to_string_pretty(&states, PrettyConfig::default())?,
+    )?;
+
+    let enabled = states.values().filter(|v| **v).count();
+    println!(
+        "Saved preset {} ({} enabled, {} disabled)",
+        name.bright_cyan(),
+        enabled.bright_green(),
+        (states.len() - enabled).bright_red()
+    );
+
+    Ok(())
+}
+
+fn apply(profile: &Path, name: &str) -> Result<()> {
+    let preset = load(profile, name)?;
+    let installed = submod_states(profile)?;
+
+    let mut enabled_mods = match find_enabled_mods(profile) {
+        Some(mods) => mods,
+        None => EnabledMods::default_with_path(profile.join("enabledmods.json")),
+    };
+
+    let mut missing = vec![];
+    for (submod, state) in &preset {
+        let Some(current) = installed.get(submod) else {
+            missing.push(submod);
+            continue;
+        };
+
+        if current == state {
+            continue;
+        }
+
+        enabled_mods.set(submod, *state);
+        if *state {
+            println!("Enabled {}", submod.bright_green());
+        } else {
+            println!("Disabled {}", submod.bright_red());
+        }
+    }
+
+    enabled_mods.save()?;
+
+    if !missing.is_empty() {
+        println!(
+            "\n{} The following mods in this preset are no longer installed:",
+            "!!".bright_red()
+        );
+        for m in missing {
+            println!("    {}", m.bright_cyan());
+        }
+    }
+
+    println!("Applied preset {}", name.bright_cyan());
+
+    Ok(())
+}
+
+fn list(profile: &Path) -> Result<()> {
+    let presets = find_presets(profile)?;
+
+    if !std::io::stdout().is_terminal() {
+        let out = std::io::stdout();
+        for p in presets {
+            if let Err(e) = writeln!(out.lock(), "{p}")
+                && e.kind() != ErrorKind::BrokenPipe
+            {
+                return Err(e.into());
+            }
+        }
+
+        return Ok(());
+    }
+
+    if presets.is_empty() {
+        println!("No presets found");
+        return Ok(());
+    }
+
+    println!(
+        "Presets for profile {}:",
+        CONFIG.current_profile().bright_purple().bold()
+    );
+    for p in presets {
+        println!("-  {}", p.bright_cyan());
+    }
+
+    Ok(())
+}
+
+fn diff(profile: &Path, name: &str) -> Result<()> {
+    let preset = load(profile, name)?;
+    let installed = submod_states(profile)?;
+
+    let mut same = true;
+    for (submod, state) in &preset {
+        match installed.get(submod) {
+            None => println!("  {} {} (not installed)", "-".bright_red(), submod),
+            Some(current) if current != state => println!(
+                "  {} {} ({} -> {})",
+                "~".bright_yellow(),
+                submod,
+                fmt_state(*current),
+                fmt_state(*state)
+            ),
+            Some(_) => continue,
+        }
+        same = false;
+    }
+
+    for submod in installed.keys().filter(|k| !preset.contains_key(*k)) {
+        println!("  {} {} (not in preset)", "+".bright_green(), submod);
+        same = false;
+    }
+
+    if same {
+        println!("The current profile matches preset {}", name.bright_cyan());
+    }
+
+    Ok(())
+}
+
+fn fmt_state(enabled: bool) -> String {
+    if enabled {
+        "enabled".bright_green().to_string()
+    } else {
+        "disabled".bright_red().to_string()
+    }
+}
+
+fn delete(profile: &Path, name: &str, yes: bool) -> Result<()> {
+    let path = preset_path(profile, name)?;
+    if !path.try_exists()? {
+        println!("Preset {} doesn't exist", name.bright_cyan());
+        return Err(anyhow!("Preset not found"));
+    }
+
+    let ans = get_answer!(yes, "Delete preset {}? [y/N]: ", name)?;
+    if !yes && !ans.is_yes() {
+        println!("Not deleting preset");
+        return Ok(());
+    }
+
+    trash(path, format!("preset delete {name}"))?;
+    println!("Deleted preset {}", name.bright_cyan());
+
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use indicatif::{ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thermite::CORE_MODS;
+use time::{OffsetDateTime, macros::format_description};
+use tracing::{debug, warn};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    config::{CONFIG, DIRS},
+    get_answer,
+    model::{ModName, PackageIndex},
+    traits::{Answer, Index},
+    utils::{
+        cached_northstar, download_northstar, ensure_dir, find_packages, init_msg,
+        northstar_version,
+    },
+};
+
+use super::{StagedProfile, install_shared_northstar, sync};
+
+type Packages = Vec<(ModName, PathBuf)>;
+
+/// Name of the manifest stored at the root of every backup
+const MANIFEST: &str = "papa-backup.ron";
+
+/// Describes what was left out of a backup and has to be reinstalled when restoring it
+#[derive(Serialize, Deserialize)]
+struct BackupManifest {
+    profile: String,
+    #[serde(with = "time::serde::rfc3339")]
+    created: OffsetDateTime,
+    #[serde(default)]
+    northstar: Option<Version>,
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+fn backups_dir() -> PathBuf {
+    DIRS.data_dir().join("backups")
+}
+
+pub(super) fn backup_profile(
+    name: &str,
+    output: Option<PathBuf>,
+    keep: Option<usize>,
+) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let prof = game.join(name);
+    if !prof.try_exists()? {
+        println!("Profile {} doesn't exist", name.bright_cyan());
+        return Err(anyhow!("Profile not found"));
+    }
+
+    let created = OffsetDateTime::now_utc();
+    let default_output = output.is_none();
+    let output = match output {
+        Some(path) => path,
+        None => {
+            let stamp = created.format(format_description!(
+                "[year][month][day]-[hour][minute][second]"
+            ))?;
+            ensure_dir(backups_dir())?;
+            backups_dir().join(format!("{name}-{stamp}.zip"))
+        }
+    };
+
+    let northstar = northstar_version(&prof);
+    let (redownloadable, kept) = split_packages(&prof)?;
+
+    let mut excluded: Vec<PathBuf> = redownloadable
+        .iter()
+        .map(|(_, path)| path.clone())
+        .collect();
+    if northstar.is_some() {
+        for core in CORE_MODS {
+            excluded.extend(
+                fs::read_dir(prof.join("mods"))?
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| {
+                        p.file_name()
+                            .is_some_and(|n| n.to_string_lossy().to_lowercase() == core)
+                    }),
+            );
+        }
+    }
+
+    let manifest = BackupManifest {
+        profile: name.to_string(),
+        created,
+        northstar,
+        packages: redownloadable
+            .iter()
+            .map(|(name, _)| name.as_modstr().to_string())
+            .collect(),
+    };
+
+    let bar = ProgressBar::new_spinner()
+        .with_style(
+            ProgressStyle::with_template("{prefix}{spinner:.cyan}")?
+                .tick_strings(&["   ", ".  ", ".. ", "...", "   "]),
+        )
+        .with_prefix(format!("Backing up profile {}", name.bright_cyan()));
+    bar.enable_steady_tick(Duration::from_millis(500));
+
+    let mut zip = ZipWriter::new(File::create(&output)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(ron::ser::to_string_pretty(&manifest, Default::default())?.as_bytes())?;
+    add_dir(&mut zip, &prof, &prof, &excluded, options)?;
+    zip.finish()?;
+    bar.finish_and_clear();
+
+    println!(
+        "Backed up profile {} to {}",
+        name.bright_cyan(),
+        output.display().bright_cyan()
+    );
+    if !kept.is_empty() {
+        println!(
+            "{} packages couldn't be found in the package index and were included in full",
+            kept.len().bold()
+        );
+    }
+
+    if default_output && let Some(keep) = keep.or(CONFIG.backup_keep()) {
+        prune_backups(name, keep)?;
+    }
+
+    Ok(())
+}
+
+/// Split a profile's packages into ones that can be redownloaded and ones that have to be archived
+///
+/// Only packages whose exact version is in the index are left out, restoring reinstalls them from
+/// there and cached zips can be evicted at any time.
+fn split_packages(prof: &Path) -> Result<(Packages, Packages)> {
+    let index = match PackageIndex::fetch() {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("Unable to fetch the package index, every package will be included in full");
+            debug!("{e}");
+            PackageIndex::new(vec![])
+        }
+    };
+
+    Ok(find_packages(prof.join("packages"))?
+        .into_iter()
+        .filter(|(name, _)| name.author.to_lowercase() != "northstar")
+        .partition(|(name, _)| {
+            index.get_item(name).is_some_and(|m| {
+                name.version
+                    .as_ref()
+                    .is_some_and(|v| m.get_version(v.to_string()).is_some())
+            })
+        }))
+}
+
+fn add_dir(
+    zip: &mut ZipWriter<File>,
+    root: &Path,
+    dir: &Path,
+    excluded: &[PathBuf],
+    options: SimpleFileOptions,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if excluded.contains(&path) {
+            debug!("Leaving '{}' out of the backup", path.display());
+            continue;
+        }
+
+        let rel = path
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        if path.is_dir() {
+            zip.add_directory(rel, options)?;
+            add_dir(zip, root, &path, excluded, options)?;
+        } else {
+            zip.start_file(rel, options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove all but the newest `keep` backups of a profile from the default backup directory
+fn prune_backups(name: &str, keep: usize) -> Result<()> {
+    let prefix = format!("{name}-");
+    let mut backups: Vec<PathBuf> = fs::read_dir(backups_dir())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension().is_some_and(|ext| ext == "zip")
+                && p.file_stem().is_some_and(|stem| {
+                    stem.to_string_lossy()
+                        .strip_prefix(&prefix)
+                        .is_some_and(|stamp| {
+                            stamp.len() == 15
+                                && stamp.chars().all(|c| c.is_ascii_digit() || c == '-')
+                        })
+                })
+        })
+        .collect();
+
+    // the timestamp format sorts in chronological order
+    backups.sort();
+    let count = backups.len().saturating_sub(keep);
+    for old in backups.into_iter().take(count) {
+        debug!("Removing old backup '{}'", old.display());
+        fs::remove_file(old)?;
+    }
+
+    if count > 0 {
+        println!("Removed {} old backups", count.bold());
+    }
+
+    Ok(())
+}
+
+pub(super) fn restore_profile(
+    archive: &Path,
+    name: Option<&str>,
+    force: bool,
+    yes: bool,
+    no_cache: bool,
+) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let manifest: BackupManifest = {
+        let mut raw = String::new();
+        zip.by_name(MANIFEST)
+            .map_err(|_| anyhow!("'{}' isn't a papa profile backup", archive.display()))?
+            .read_to_string(&mut raw)?;
+        ron::from_str(&raw)?
+    };
+
+    let name = name.unwrap_or(&manifest.profile);
+    let prof = game.join(name);
+    if prof.try_exists()? {
+        if !force {
+            println!(
+                "Profile {} already exists, use {} to replace it",
+                name.bright_cyan(),
+                "--force".bright_cyan()
+            );
+            return Err(anyhow!("Profile already exists"));
+        }
+
+        let ans = get_answer!(
+            yes,
+            "Replace existing profile {}? [y/N]: ",
+            name.bright_red()
+        )?;
+        if !yes && !ans.is_yes() {
+            println!("Not replacing existing profile, aborting");
+            return Ok(());
+        }
+    }
+
+    // restore next to the profile and only swap it in once everything is installed
+    let staged = StagedProfile::begin(&prof)?;
+
+    if let Some(version) = &manifest.northstar {
+        let file = if !no_cache && let Some(nstar) = cached_northstar(Some(version))? {
+            nstar
+        } else {
+            let ans = get_answer!(yes, "Download Northstar {}? [Y/n] ", version)?;
+            if ans.is_no() {
+                println!("Not downloading Northstar, aborting");
+                return Err(anyhow!("Profile not restored"));
+            }
+            download_northstar(Some(version.clone()))?
+        };
+
+        println!(
+            "Installing Northstar {}",
+            format!("v{version}").bright_cyan()
+        );
+        install_shared_northstar(file, staged.path())?;
+    }
+
+    zip.extract(staged.path())?;
+    fs::remove_file(staged.path().join(MANIFEST))?;
+
+    let mods = manifest
+        .packages
+        .iter()
+        .filter_map(|p| ModName::try_from(p.as_str()).ok())
+        .collect();
+    sync::install_exact(mods, staged.path(), no_cache, false)?;
+
+    staged.finish(format!("profile restore --force {name}"))?;
+
+    println!(
+        "Restored profile {} from backup made {}",
+        name.bright_cyan(),
+        manifest
+            .created
+            .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))?
+    );
+
+    Ok(())
+}
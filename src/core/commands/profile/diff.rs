@@ -0,0 +1,150 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    config::CONFIG,
+    core::commands::change::{Change, diff_maps, print_change},
+    model::ModName,
+    utils::{find_packages, init_msg, northstar_version, submod_states},
+};
+
+use super::ProfileMeta;
+
+#[derive(Serialize)]
+struct ProfileDiff {
+    a: String,
+    b: String,
+    northstar: Option<Change<String>>,
+    packages: Vec<Change<Vec<String>>>,
+    submods: Vec<Change<bool>>,
+    run_args: Option<Change<String>>,
+}
+
+impl ProfileDiff {
+    fn is_empty(&self) -> bool {
+        self.northstar.is_none()
+            && self.packages.is_empty()
+            && self.submods.is_empty()
+            && self.run_args.is_none()
+    }
+}
+
+pub(super) fn diff_profiles(a: &str, b: &str, json: bool) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let dir_a = game.join(a);
+    let dir_b = game.join(b);
+    for (name, dir) in [(a, &dir_a), (b, &dir_b)] {
+        if !dir.try_exists()? {
+            println!("Profile {} doesn't exist", name.bright_cyan());
+            return Err(anyhow!("Profile not found"));
+        }
+    }
+
+    let ns_a = northstar_version(&dir_a).map(|v| v.to_string());
+    let ns_b = northstar_version(&dir_b).map(|v| v.to_string());
+
+    let diff = ProfileDiff {
+        a: a.to_string(),
+        b: b.to_string(),
+        northstar: Change::compare("Northstar", ns_a, ns_b),
+        packages: diff_maps(package_versions(&dir_a)?, package_versions(&dir_b)?),
+        submods: diff_maps(submod_states(&dir_a)?, submod_states(&dir_b)?),
+        run_args: Change::compare("papa run", run_args(&dir_a)?, run_args(&dir_b)?),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    print_diff(&diff);
+
+    Ok(())
+}
+
+/// Every installed version of each package, oldest first
+fn package_versions(profile: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut versions: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    for (name, _) in find_packages(profile.join("packages"))? {
+        versions
+            .entry(ModName::new(&name.author, &name.name, None).to_string())
+            .or_default()
+            .push(name.version);
+    }
+
+    Ok(versions
+        .into_iter()
+        .map(|(name, mut found)| {
+            found.sort();
+            let found = found
+                .into_iter()
+                .map(|v| v.map(|v| v.to_string()).unwrap_or_default())
+                .collect();
+            (name, found)
+        })
+        .collect())
+}
+
+/// The args `papa run` launches a profile with
+///
+/// The launcher's own args files live in the game directory and are shared by every profile, so
+/// they're left out of the diff.
+fn run_args(profile: &Path) -> Result<Option<String>> {
+    let run_args = ProfileMeta::load(profile)?.run_args;
+    Ok((!run_args.is_empty()).then(|| run_args.join(" ")))
+}
+
+fn print_diff(diff: &ProfileDiff) {
+    if diff.is_empty() {
+        println!(
+            "Profiles {} and {} are the same",
+            diff.a.bright_cyan(),
+            diff.b.bright_cyan()
+        );
+        return;
+    }
+
+    println!(
+        "Comparing {} with {}",
+        diff.a.bright_red().bold(),
+        diff.b.bright_green().bold()
+    );
+
+    if let Some(ns) = &diff.northstar {
+        println!("\nNorthstar:");
+        print_change(ns, 2, |v| format!("v{v}"));
+    }
+
+    if !diff.packages.is_empty() {
+        println!("\nPackages:");
+        for change in &diff.packages {
+            print_change(change, 2, |versions| {
+                versions
+                    .iter()
+                    .map(|v| format!("v{v}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+        }
+    }
+
+    if !diff.submods.is_empty() {
+        println!("\nMods:");
+        for change in &diff.submods {
+            print_change(change, 2, |enabled| {
+                if *enabled { "enabled" } else { "disabled" }.to_string()
+            });
+        }
+    }
+
+    if let Some(run_args) = &diff.run_args {
+        println!("\nLaunch args:");
+        print_change(run_args, 2, |args| format!("'{args}'"));
+    }
+}
@@ -0,0 +1,61 @@
+use std::{collections::BTreeSet, fs, io::ErrorKind, path::Path};
+
+use anyhow::Result;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+const META_FILE: &str = "papa-profile.toml";
+
+/// Optional metadata stored alongside a profile
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ProfileMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub created: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloned_from: Option<String>,
+    /// Version of Northstar this profile should stay on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub northstar: Option<Version>,
+    /// Args passed to the game by `papa run` when none are given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run_args: Vec<String>,
+    /// Packages that `papa update` should leave alone, as `Author.Name`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub pinned: BTreeSet<String>,
+}
+
+impl ProfileMeta {
+    pub fn new() -> Self {
+        Self {
+            created: Some(OffsetDateTime::now_utc()),
+            ..Default::default()
+        }
+    }
+
+    /// Load the metadata for a profile, or the default if it doesn't have any
+    pub fn load(profile: impl AsRef<Path>) -> Result<Self> {
+        match fs::read_to_string(profile.as_ref().join(META_FILE)) {
+            Ok(raw) => Ok(toml::from_str(&raw)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn is_pinned(&self, author: &str, name: &str) -> bool {
+        let key = format!("{author}.{name}").to_lowercase();
+        self.pinned.iter().any(|p| p.to_lowercase() == key)
+    }
+
+    pub fn save(&self, profile: impl AsRef<Path>) -> Result<()> {
+        let raw = toml::to_string_pretty(self)?;
+        fs::write(profile.as_ref().join(META_FILE), raw)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,837 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{ErrorKind, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use clap::{Args, Subcommand, ValueHint};
+use clap_complete::ArgValueCompleter;
+use indicatif::{ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+use semver::Version;
+use thermite::{CORE_MODS, core::manage::install_northstar_profile};
+use time::macros::format_description;
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, DIRS, default_profile},
+    core::trash::trash,
+    get_answer,
+    traits::Answer,
+    update_cfg,
+    utils::{
+        CopyMode, cached_northstar, copy_dir_with_progress, did_you_mean, dir_size,
+        download_northstar, ensure_dir, init_msg, northstar_version, suggest,
+    },
+};
+
+mod backup;
+mod diff;
+mod meta;
+mod sync;
+mod template;
+mod usage;
+
+pub use meta::ProfileMeta;
+pub(crate) use sync::{apply_states, install_exact};
+
+/// Directories whose contents can be shared between profiles when cloning
+const SHARED_DIRS: [&str; 2] = ["packages", "mods"];
+
+#[derive(Subcommand)]
+pub enum ProfileCommands {
+    #[clap(alias = "s", alias = "choose", alias = "activate")]
+    ///Select a profile
+    Select {
+        ///Name of the profile to select
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        name: String,
+    },
+    ///Ignore a directory, preventing it from being displayed as a profile
+    Ignore {
+        #[clap(value_hint = ValueHint::DirPath)]
+        name: String,
+    },
+    ///Un-ignore a directory, allowing it to be displayed as a profile
+    Unignore {
+        #[clap(value_hint = ValueHint::DirPath)]
+        name: String,
+    },
+    #[clap(alias("ls"))]
+    ///List profiles
+    List {
+        ///Show each profile's metadata
+        #[arg(long, short)]
+        long: bool,
+    },
+    ///Create a new profile
+    #[clap(alias("n"))]
+    New {
+        ///Name of the profile to create
+        #[clap(value_hint = ValueHint::DirPath)]
+        name: OsString,
+
+        #[command(flatten)]
+        options: NewOptions,
+    },
+
+    #[clap(alias = "dupe", alias = "cp", alias = "copy")]
+    ///Clone an existing profile
+    Clone {
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        source: String,
+        #[clap(value_hint = ValueHint::DirPath)]
+        new: Option<String>,
+        ///Replace any existing profile of the same name
+        #[arg(long, short)]
+        force: bool,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+        ///Copy every file instead of sharing unchanged mod files with the source profile
+        #[arg(long, conflicts_with = "hardlink")]
+        copy: bool,
+        ///Hardlink mod files that can't be shared through copy-on-write reflinks
+        ///
+        ///Editing a hardlinked file in place changes it in both profiles
+        #[arg(long)]
+        hardlink: bool,
+        ///Description of the new profile
+        #[arg(long, short)]
+        description: Option<String>,
+    },
+
+    #[clap(alias = "rm", alias = "remove")]
+    ///Delete a profile and everything in it
+    Delete {
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        name: String,
+        ///Allow deleting the active profile
+        #[arg(long, short)]
+        force: bool,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    ///Show how much disk space each profile uses, and how much of it is shared
+    Du {
+        ///Only show these profiles
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        names: Vec<String>,
+    },
+
+    #[clap(alias = "mv", alias = "move")]
+    ///Rename a profile
+    Rename {
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        name: String,
+        #[clap(value_hint = ValueHint::DirPath)]
+        new: String,
+    },
+
+    ///Compare the packages, mods and settings of two profiles
+    Diff {
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        a: String,
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        b: String,
+        ///Output the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    ///Install or remove packages in a profile to match another profile
+    Sync {
+        ///Profile to copy the package list from
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        source: String,
+        ///Profile to install packages into
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        target: String,
+        ///Also remove any packages that aren't in the source profile
+        #[arg(long, short)]
+        mirror: bool,
+        ///Also copy the enabled state of each mod
+        #[arg(long, short)]
+        enabled: bool,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    ///Archive a profile's configs, save data and mods
+    ///
+    ///Packages that can be downloaded again are left out and listed in the archive instead
+    Backup {
+        ///Profile to back up, defaults to the current profile
+        #[clap(add = ArgValueCompleter::new(crate::completers::profiles))]
+        name: Option<String>,
+        ///File to write the backup to, defaults to papa's data directory
+        #[arg(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        ///Only keep this many backups of the profile in papa's data directory
+        #[arg(long, short)]
+        keep: Option<usize>,
+    },
+
+    ///Recreate a profile from a backup, reinstalling any packages it lists
+    Restore {
+        #[clap(value_hint = ValueHint::FilePath)]
+        archive: PathBuf,
+        ///Name of the restored profile, defaults to the name it was backed up under
+        #[arg(long = "as", value_name = "NAME")]
+        name: Option<String>,
+        ///Replace any existing profile of the same name
+        #[arg(long, short)]
+        force: bool,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Args, Clone)]
+pub struct NewOptions {
+    ///Don't inlcude Norhtstar core files and mods
+    #[arg(long, short)]
+    empty: bool,
+    ///Remove any existing folder of the same name
+    #[arg(long, short)]
+    force: bool,
+    ///Answer "yes" to any prompts
+    #[arg(long, short)]
+    yes: bool,
+    ///The version of Northstar to use when for this profile
+    ///
+    /// Leave unset for latest
+    #[arg(long, short, conflicts_with = "empty")]
+    version: Option<Version>,
+    ///Copy Northstar's core files instead of sharing them with other profiles
+    #[arg(long, conflicts_with = "empty")]
+    copy: bool,
+    ///Description of the profile
+    #[arg(long, short)]
+    description: Option<String>,
+    ///Keep this profile on its version of Northstar when running 'papa ns update'
+    #[arg(long, conflicts_with = "empty")]
+    pin: bool,
+    ///Default args to pass to the game when running this profile
+    #[arg(long = "args", value_name = "ARGS", allow_hyphen_values = true)]
+    run_args: Option<String>,
+    ///Install the packages from a template, another profile, or a file made by 'papa export'
+    ///
+    ///Only the package list and enabled mods are copied from other profiles, not configs or saves
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "empty")]
+    #[clap(add = ArgValueCompleter::new(crate::completers::templates))]
+    from: Option<String>,
+}
+
+pub fn handle(command: &ProfileCommands, no_cache: bool) -> Result<()> {
+    match command {
+        ProfileCommands::List { long } => list_profiles(*long),
+        ProfileCommands::New { name, options } => new_profile(name, options.clone(), no_cache),
+        ProfileCommands::Clone {
+            source,
+            new,
+            force,
+            yes,
+            copy,
+            hardlink,
+            description,
+        } => {
+            let mode = if *copy {
+                CopyMode::Copy
+            } else if *hardlink {
+                CopyMode::Hardlink
+            } else {
+                CopyMode::Reflink
+            };
+            clone_profile(source, new, *force, *yes, mode, description.clone())
+        }
+        ProfileCommands::Backup { name, output, keep } => backup::backup_profile(
+            name.as_deref().unwrap_or(CONFIG.current_profile()),
+            output.clone(),
+            *keep,
+        ),
+        ProfileCommands::Restore {
+            archive,
+            name,
+            force,
+            yes,
+        } => backup::restore_profile(archive, name.as_deref(), *force, *yes, no_cache),
+        ProfileCommands::Du { names } => usage::disk_usage(names),
+        ProfileCommands::Delete { name, force, yes } => delete_profile(name, *force, *yes),
+        ProfileCommands::Rename { name, new } => rename_profile(name, new),
+        ProfileCommands::Select { name } => activate_profile(name),
+        ProfileCommands::Diff { a, b, json } => diff::diff_profiles(a, b, *json),
+        ProfileCommands::Sync {
+            source,
+            target,
+            mirror,
+            enabled,
+            yes,
+        } => sync::sync_profiles(source, target, *mirror, *enabled, *yes, no_cache),
+        ProfileCommands::Ignore { name } => {
+            update_cfg!(ignore(name))?;
+            println!("Added {} to ignore list", name.bright_cyan());
+            Ok(())
+        }
+        ProfileCommands::Unignore { name } => {
+            update_cfg!(unignore(name))?;
+            println!("Removed {} from ignore list", name.bright_cyan());
+            Ok(())
+        }
+    }
+}
+
+fn activate_profile(name: &String) -> Result<()> {
+    let Some(dir) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    if CONFIG.is_ignored(name) {
+        println!(
+            "Directory {} is on the ignore list. Please run '{}' and try again.",
+            name.bright_red(),
+            format!("papa profile unignore {name}").bright_cyan()
+        );
+        return Err(anyhow!("Profile was ignored"));
+    }
+
+    let similar;
+    let name = if dir.join(name).try_exists()? {
+        name
+    } else {
+        println!("Profile {} doesn't exist", name.bright_cyan());
+        let profiles = find_profiles(dir)?;
+        let names = profiles
+            .iter()
+            .filter_map(|p| p.file_name()?.to_str())
+            .collect::<Vec<_>>();
+        let Some(found) = did_you_mean(&suggest(name, names), false)? else {
+            return Err(anyhow!("Profile not found"));
+        };
+        similar = found.to_string();
+        &similar
+    };
+
+    update_cfg!(profile(name))?;
+
+    println!("Made {} the active profile", name.bright_cyan());
+
+    Ok(())
+}
+
+pub fn find_profiles(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let mut profiles = vec![];
+    for candidate in dir.as_ref().read_dir()? {
+        let candidate = candidate?;
+        if !candidate.file_type()?.is_dir()
+            || CONFIG.is_ignored(
+                candidate
+                    .file_name()
+                    .to_str()
+                    .expect("Unable to convert from OsString"),
+            )
+        {
+            continue;
+        }
+
+        let path = candidate.path();
+        profiles.push(path);
+    }
+
+    Ok(profiles)
+}
+
+fn list_profiles(long: bool) -> Result<()> {
+    let Some(dir) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let profiles = find_profiles(dir)?;
+
+    // output the raw list if we're in a script or pipeline
+    if !std::io::stdout().is_terminal() {
+        let out = std::io::stdout();
+        for p in profiles {
+            if let Some(name) = p.file_name().and_then(|os| os.to_str()) {
+                if let Err(e) = writeln!(out.lock(), "{name}") {
+                    if e.kind() != ErrorKind::BrokenPipe {
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    if profiles.is_empty() {
+        println!("No profiles found");
+        return Ok(());
+    }
+
+    println!("Available profiles:");
+    for path in profiles.iter() {
+        let Some(name) = path.file_name().and_then(|os| os.to_str()) else {
+            continue;
+        };
+
+        println!(
+            "{:<4}{}",
+            if name == CONFIG.current_profile() {
+                "-->"
+            } else {
+                ""
+            }
+            .bright_green(),
+            name.bright_cyan()
+        );
+
+        if long {
+            print_meta(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_meta(profile: &Path) -> Result<()> {
+    let meta = ProfileMeta::load(profile)?;
+
+    if let Some(desc) = &meta.description {
+        println!("    {desc}");
+    }
+    if let Some(version) = northstar_version(profile) {
+        print!("    Northstar: {}", format!("v{version}").bright_cyan());
+        if let Some(pinned) = &meta.northstar {
+            print!(" (pinned to {})", format!("v{pinned}").bright_yellow());
+        }
+        println!();
+    }
+    if let Some(created) = meta.created {
+        let fmt = format_description!("[year]-[month]-[day] [hour]:[minute]");
+        println!("    Created: {}", created.format(&fmt)?);
+    }
+    if let Some(source) = &meta.cloned_from {
+        println!("    Cloned from: {}", source.bright_cyan());
+    }
+    if !meta.run_args.is_empty() {
+        println!("    Run args: {}", meta.run_args.join(" ").bright_cyan());
+    }
+
+    Ok(())
+}
+
+fn new_profile(name: &OsString, options: NewOptions, no_cache: bool) -> Result<()> {
+    let Some(dir) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let prof = dir.join(name);
+    if prof.try_exists()? {
+        if options.force {
+            let ans = get_answer!(
+                options.yes,
+                "Remove existing folder {}? [y/N]: ",
+                name.display().bright_red()
+            )?;
+            if !options.yes && !ans.is_yes() {
+                println!("Not removing existing folder, aborting");
+                return Ok(());
+            }
+        } else {
+            println!("A folder of that name already exists, remove it first");
+            return Ok(());
+        }
+    }
+    let template = options
+        .from
+        .as_deref()
+        .map(|from| template::load(dir, from))
+        .transpose()?;
+
+    // build the profile next to where it goes, so a failure leaves any existing one untouched
+    let staged = StagedProfile::begin(&prof)?;
+
+    if !options.empty {
+        let file = if !no_cache && let Some(nstar) = cached_northstar(options.version.as_ref())? {
+            nstar
+        } else {
+            let ans = if let Some(version) = options.version.as_ref() {
+                get_answer!(options.yes, "Download Northstar {}? [Y/n] ", version)?
+            } else {
+                get_answer!(options.yes, "Download latest Northstar? [Y/n] ")?
+            };
+
+            if ans.is_no() {
+                println!(
+                    "Not downloading Northstar, use {} to create a profile without it",
+                    "--empty".bright_cyan()
+                );
+                return Err(anyhow!("Profile not created"));
+            } else {
+                download_northstar(options.version)?
+            }
+        };
+
+        let bar = ProgressBar::new_spinner()
+            .with_style(
+                ProgressStyle::with_template("{prefix}{spinner:.cyan}")?
+                    .tick_strings(&["   ", ".  ", ".. ", "...", "   "]),
+            )
+            .with_prefix("Installing Northstar core files");
+        bar.enable_steady_tick(Duration::from_millis(500));
+        if options.copy {
+            install_northstar_profile(file, staged.path())?;
+        } else {
+            install_shared_northstar(file, staged.path())?;
+        }
+        bar.finish();
+    }
+
+    if let Some(template) = &template {
+        template::apply(template, staged.path(), no_cache)?;
+    }
+
+    let mut meta = ProfileMeta::new();
+    meta.description = options
+        .description
+        .or_else(|| template.as_ref().and_then(|t| t.description.clone()));
+    meta.run_args = match options.run_args {
+        Some(args) => args.split_whitespace().map(String::from).collect(),
+        None => template.map(|t| t.run_args).unwrap_or_default(),
+    };
+    if options.pin {
+        meta.northstar = northstar_version(staged.path());
+    }
+    meta.save(staged.path())?;
+
+    staged.finish(format!("profile new --force {}", name.display()))?;
+
+    println!("Created profile {}", name.display().bright_cyan());
+
+    Ok(())
+}
+
+/// A profile being built in a hidden folder next to where it will end up
+///
+/// The folder is removed when this is dropped without being finished, so a failed or cancelled
+/// build never touches an existing profile of the same name.
+struct StagedProfile {
+    path: PathBuf,
+    dest: PathBuf,
+    done: bool,
+}
+
+impl StagedProfile {
+    fn begin(dest: &Path) -> Result<Self> {
+        let name = dest
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid profile path '{}'", dest.display()))?;
+        let mut hidden = OsString::from(".papa-new-");
+        hidden.push(name);
+        let path = dest.with_file_name(hidden);
+        if path.try_exists()? {
+            debug!("Removing leftover folder '{}'", path.display());
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir(&path)?;
+
+        Ok(Self {
+            path,
+            dest: dest.to_path_buf(),
+            done: false,
+        })
+    }
+
+    /// The folder to build the profile in
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the finished profile into place, sending any existing profile to the trash
+    fn finish(mut self, reason: impl Into<String>) -> Result<()> {
+        if self.dest.try_exists()? {
+            trash(&self.dest, reason)?;
+        }
+        fs::rename(&self.path, &self.dest)?;
+        self.done = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for StagedProfile {
+    fn drop(&mut self) {
+        if !self.done
+            && self.path.exists()
+            && let Err(e) = fs::remove_dir_all(&self.path)
+        {
+            debug!("Unable to remove '{}': {e}", self.path.display());
+        }
+    }
+}
+
+/// Extract Northstar into the store of shared versions, returning the directory it ended up in
+fn store_northstar(file: File) -> Result<PathBuf> {
+    let store = DIRS.data_dir().join("northstar");
+    let staging = store.join(".staging");
+    if staging.try_exists()? {
+        fs::remove_dir_all(&staging)?;
+    }
+    ensure_dir(&staging)?;
+    install_northstar_profile(file, &staging)?;
+
+    let version = northstar_version(&staging)
+        .ok_or_else(|| anyhow!("Unable to read the version of the extracted Northstar files"))?;
+    let shared = store.join(version.to_string());
+    if shared.try_exists()? {
+        fs::remove_dir_all(&staging)?;
+    } else {
+        fs::rename(&staging, &shared)?;
+    }
+
+    Ok(shared)
+}
+
+/// Install Northstar's core files to a profile, sharing them through a store of extracted versions
+pub(crate) fn install_shared_northstar(file: File, prof: &Path) -> Result<()> {
+    let shared = store_northstar(file)?;
+
+    // never hardlink from the store, an edit in one profile would leak into every new profile
+    copy_dir_with_progress(&shared, prof, CopyMode::Reflink, &ProgressBar::hidden())
+}
+
+/// Swap a profile's core mods for another version of Northstar
+///
+/// The new core mods are extracted and copied next to the profile before the old ones are moved to
+/// the trash, so a failed download or extraction leaves the profile as it was.
+pub(crate) fn replace_shared_northstar(file: File, prof: &Path, reason: &str) -> Result<()> {
+    let shared = store_northstar(file)?;
+
+    let staging = prof.join(".papa-core-staging");
+    if staging.try_exists()? {
+        fs::remove_dir_all(&staging)?;
+    }
+    if let Err(e) = copy_dir_with_progress(
+        shared.join("mods"),
+        &staging,
+        CopyMode::Reflink,
+        &ProgressBar::hidden(),
+    ) {
+        fs::remove_dir_all(&staging)?;
+        return Err(e);
+    }
+
+    let mods = prof.join("mods");
+    ensure_dir(&mods)?;
+    for entry in fs::read_dir(&mods)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if CORE_MODS.contains(&name.as_str()) {
+            debug!("Removing old core mod '{}'", entry.path().display());
+            trash(entry.path(), reason)?;
+        }
+    }
+    for entry in fs::read_dir(&staging)? {
+        let entry = entry?;
+        fs::rename(entry.path(), mods.join(entry.file_name()))?;
+    }
+    fs::remove_dir_all(&staging)?;
+
+    Ok(())
+}
+
+fn clone_profile(
+    source: &String,
+    new: &Option<String>,
+    force: bool,
+    yes: bool,
+    mode: CopyMode,
+    description: Option<String>,
+) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+    let source_dir = game.join(source);
+    if !source_dir.try_exists()? {
+        println!("Profile {} doesn't exist", source.bright_cyan());
+        return Err(anyhow!("Profile not found"));
+    }
+
+    let target_dir = if let Some(target) = new {
+        game.join(target)
+    } else {
+        game.join(format!("{source}-copy"))
+    };
+    let target_name = target_dir
+        .file_name()
+        .expect("Missing file name?")
+        .to_string_lossy();
+
+    if target_dir.try_exists()? {
+        if !force {
+            println!(
+                "Profile '{}' already exists, use {} to replace it",
+                target_name.bright_green(),
+                "--force".bright_cyan()
+            );
+            return Err(anyhow!("Profile already exists"));
+        }
+
+        let ans = get_answer!(
+            yes,
+            "Replace existing profile {}? [y/N]: ",
+            target_name.bright_red()
+        )?;
+        if !yes && !ans.is_yes() {
+            println!("Not replacing existing profile, aborting");
+            return Ok(());
+        }
+        trash(&target_dir, format!("profile clone --force {target_name}"))?;
+    }
+
+    if mode == CopyMode::Hardlink {
+        println!(
+            "{} Hardlinked files are shared with {}, editing one in place changes it in both profiles",
+            "!!".bright_yellow(),
+            source.bright_cyan()
+        );
+    }
+
+    let pb = ProgressBar::new(dir_size(&source_dir)?)
+        .with_style(
+            ProgressStyle::with_template("{msg}{bar:.cyan} {bytes}/{total_bytes} {duration}")?
+                .progress_chars(".. "),
+        )
+        .with_message(format!("Cloning {} ", source.bright_cyan()));
+    fs::create_dir_all(&target_dir)?;
+    for entry in source_dir.read_dir()? {
+        let entry = entry?;
+        let target = target_dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            let mode = if SHARED_DIRS.iter().any(|d| entry.file_name() == *d) {
+                mode
+            } else {
+                CopyMode::Copy
+            };
+            copy_dir_with_progress(entry.path(), target, mode, &pb)?;
+        } else {
+            pb.inc(fs::copy(entry.path(), target)?);
+        }
+    }
+    pb.finish_and_clear();
+
+    let mut meta = ProfileMeta::load(&target_dir)?;
+    meta.created = ProfileMeta::new().created;
+    meta.cloned_from = Some(source.clone());
+    if description.is_some() {
+        meta.description = description;
+    }
+    meta.save(&target_dir)?;
+
+    println!(
+        "Cloned profile '{}' to '{}'",
+        source.bright_green(),
+        target_name.bright_green()
+    );
+
+    Ok(())
+}
+
+fn delete_profile(name: &String, force: bool, yes: bool) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let dir = game.join(name);
+    if !dir.try_exists()? {
+        println!("Profile {} doesn't exist", name.bright_cyan());
+        return Err(anyhow!("Profile not found"));
+    }
+
+    let active = name == CONFIG.current_profile();
+    if active && !force {
+        println!(
+            "Profile {} is the active profile, select another profile first or use {}",
+            name.bright_cyan(),
+            "--force".bright_cyan()
+        );
+        return Err(anyhow!("Refusing to delete the active profile"));
+    }
+
+    let ans = get_answer!(
+        yes,
+        format!(
+            "{0}This will delete profile {1} including all of its mods, configs and save data{0}\n\nAre you sure you want to continue? [y/N]: ",
+            "!!!".bright_red(),
+            name.bright_cyan()
+        )
+    )?;
+    if !yes && !ans.is_yes() {
+        println!("Not deleting profile");
+        return Ok(());
+    }
+
+    let id = trash(&dir, format!("profile delete {name}"))?;
+    println!(
+        "Deleted profile {}, run '{}' to get it back",
+        name.bright_cyan(),
+        format!("papa trash restore {id}").bright_cyan()
+    );
+
+    if active {
+        let default = default_profile();
+        if name != &default {
+            update_cfg!(profile(default))?;
+            println!(
+                "Made {} the active profile",
+                default_profile().bright_cyan()
+            );
+        } else {
+            println!(
+                "Run '{}' to choose a new active profile",
+                "papa profile select".bright_cyan()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn rename_profile(name: &String, new: &String) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let dir = game.join(name);
+    if !dir.try_exists()? {
+        println!("Profile {} doesn't exist", name.bright_cyan());
+        return Err(anyhow!("Profile not found"));
+    }
+
+    let target = game.join(new);
+    if target.try_exists()? {
+        println!("A folder named {} already exists", new.bright_cyan());
+        return Err(anyhow!("Profile already exists"));
+    }
+
+    fs::rename(&dir, &target)?;
+
+    if name == CONFIG.current_profile() {
+        update_cfg!(profile(new))?;
+    }
+
+    println!(
+        "Renamed profile '{}' to '{}'",
+        name.bright_green(),
+        new.bright_green()
+    );
+
+    Ok(())
+}
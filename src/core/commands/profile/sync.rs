@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use thermite::model::{EnabledMods, ModVersion};
+use tracing::{debug, warn};
+
+use crate::{
+    config::CONFIG,
+    core::trash::trash,
+    get_answer,
+    model::{ModName, PackageIndex},
+    traits::{Answer, Index},
+    utils::{download_and_install_to, find_enabled_mods, find_packages, init_msg, submod_states},
+};
+
+type Key = (String, String);
+
+fn key(name: &ModName) -> Key {
+    (name.author.to_lowercase(), name.name.to_lowercase())
+}
+
+pub(super) fn sync_profiles(
+    source: &str,
+    target: &str,
+    mirror: bool,
+    enabled: bool,
+    yes: bool,
+    no_cache: bool,
+) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    if source == target {
+        return Err(anyhow!("Can't sync a profile with itself"));
+    }
+
+    let source_dir = game.join(source);
+    let target_dir = game.join(target);
+    for (name, dir) in [(source, &source_dir), (target, &target_dir)] {
+        if !dir.try_exists()? {
+            println!("Profile {} doesn't exist", name.bright_cyan());
+            return Err(anyhow!("Profile not found"));
+        }
+    }
+
+    // Northstar's own packages belong to the profile's Northstar install, never sync them
+    let is_northstar = |name: &ModName| name.author.to_lowercase() == "northstar";
+
+    let packages = target_dir.join("packages");
+    let mut existing: BTreeMap<Key, (ModName, PathBuf)> = find_packages(&packages)?
+        .into_iter()
+        .filter(|(name, _)| !is_northstar(name))
+        .map(|(name, path)| (key(&name), (name, path)))
+        .collect();
+
+    let mut to_install = vec![];
+    let mut replaced = vec![];
+    for (name, _) in find_packages(source_dir.join("packages"))? {
+        if is_northstar(&name) {
+            continue;
+        }
+
+        match existing.remove(&key(&name)) {
+            None => to_install.push(name),
+            Some((old, path)) if old.version != name.version => {
+                replaced.push((name.clone(), path));
+                to_install.push(name);
+            }
+            Some(_) => debug!("{name} is already in sync"),
+        }
+    }
+
+    let to_remove: Vec<(ModName, PathBuf)> = if mirror {
+        existing.into_values().collect()
+    } else {
+        vec![]
+    };
+
+    if to_install.is_empty() && to_remove.is_empty() {
+        println!(
+            "Packages in {} are already in sync with {}",
+            target.bright_cyan(),
+            source.bright_cyan()
+        );
+    } else {
+        println!(
+            "Syncing {} from {}:",
+            target.bright_cyan().bold(),
+            source.bright_cyan().bold()
+        );
+        for name in &to_install {
+            println!("  {} {}", "+".bright_green(), name.bright_cyan());
+        }
+        for (name, _) in &to_remove {
+            println!("  {} {}", "-".bright_red(), name.bright_cyan());
+        }
+
+        let answer = get_answer!(yes)?;
+        if answer.is_no() {
+            return Ok(());
+        }
+
+        install_exact(to_install, &target_dir, no_cache, true)?;
+
+        for (name, old) in replaced {
+            if !packages.join(name.as_modstr().to_string()).try_exists()? {
+                warn!("{name} wasn't installed, keeping the old version");
+                continue;
+            }
+
+            if let Err(e) = trash(&old, format!("profile sync {name}")) {
+                warn!("Unable to remove old package folder {}", old.display());
+                debug!("{e}");
+            }
+        }
+
+        for (name, path) in to_remove {
+            println!("Removing package '{}'", name.bright_cyan());
+            trash(path, format!("profile sync --mirror {name}"))?;
+        }
+    }
+
+    if enabled {
+        sync_enabled(&source_dir, &target_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Install the exact versions of the given packages into a profile, using the cache where possible
+///
+/// With `cont` a package that fails to install is skipped, otherwise nothing is installed.
+pub(crate) fn install_exact(
+    mods: Vec<ModName>,
+    profile: &Path,
+    no_cache: bool,
+    cont: bool,
+) -> Result<()> {
+    if mods.is_empty() {
+        return Ok(());
+    }
+
+    let index = PackageIndex::fetch()?;
+    let mut valid: Vec<(ModName, &ModVersion)> = vec![];
+    for name in mods {
+        let version = index.get_item(&name).and_then(|m| {
+            name.version
+                .as_ref()
+                .map_or_else(|| m.get_latest(), |v| m.get_version(v.to_string()))
+        });
+
+        if let Some(v) = version {
+            valid.push((name, v));
+        } else {
+            println!("Couldn't find package {}, skipping", name.bright_red());
+        }
+    }
+
+    download_and_install_to(valid, profile, !no_cache, cont)?;
+
+    Ok(())
+}
+
+fn sync_enabled(source: &Path, target: &Path) -> Result<()> {
+    let changed = apply_states(target, &submod_states(source)?)?;
+    println!("Updated the enabled state of {} mods", changed.bold());
+
+    Ok(())
+}
+
+/// Set the enabled state of the mods installed in a profile, returning how many changed
+pub(crate) fn apply_states(target: &Path, states: &BTreeMap<String, bool>) -> Result<usize> {
+    let current = submod_states(target)?;
+
+    let mut enabled_mods = match find_enabled_mods(target) {
+        Some(mods) => mods,
+        None => EnabledMods::default_with_path(target.join("enabledmods.json")),
+    };
+
+    let mut changed = 0;
+    for (name, state) in states {
+        if current.get(name).is_some_and(|c| c != state) {
+            enabled_mods.set(name, *state);
+            changed += 1;
+        }
+    }
+
+    enabled_mods.save()?;
+
+    Ok(changed)
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, ProfileTemplate},
+    model::ModName,
+    utils::{find_packages, submod_states},
+};
+
+use super::{ProfileMeta, sync};
+
+/// Load a template by name, from another profile or from an export file, in that order
+pub(super) fn load(game: &Path, from: &str) -> Result<ProfileTemplate> {
+    if let Some(template) = CONFIG.template(from) {
+        debug!("Using built-in template '{from}'");
+        return Ok(template.clone());
+    }
+
+    let profile = game.join(from);
+    if !from.is_empty() && profile.join("packages").is_dir() {
+        debug!("Using profile '{}' as a template", profile.display());
+        return from_profile(&profile);
+    }
+
+    from_file(&PathBuf::from(from))
+}
+
+/// Read the package list and enabled state of a profile, leaving out configs and save data
+fn from_profile(profile: &Path) -> Result<ProfileTemplate> {
+    let packages = find_packages(profile.join("packages"))?
+        .into_iter()
+        .filter(|(name, _)| name.author.to_lowercase() != "northstar")
+        .map(|(name, _)| name.as_modstr().to_string())
+        .collect();
+
+    Ok(ProfileTemplate {
+        description: None,
+        packages,
+        enabled: submod_states(profile)?,
+        run_args: ProfileMeta::load(profile)?.run_args,
+    })
+}
+
+/// Read either a full template or a plain list of mods as written by 'papa export'
+fn from_file(file: &Path) -> Result<ProfileTemplate> {
+    let raw = match fs::read_to_string(file) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!(
+                "No template, profile or file named '{}'",
+                file.display().bright_red()
+            );
+            if !CONFIG.templates().is_empty() {
+                println!("Built-in templates:");
+                for (name, template) in CONFIG.templates() {
+                    print!("  {}", name.bright_cyan());
+                    if let Some(desc) = &template.description {
+                        print!(" - {desc}");
+                    }
+                    println!();
+                }
+            }
+            return Err(anyhow!("Template not found"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Ok(template) = ron::from_str::<ProfileTemplate>(&raw) {
+        return Ok(template);
+    }
+
+    let packages: Vec<String> =
+        ron::from_str(&raw).map_err(|e| anyhow!("Unable to parse '{}': {e}", file.display()))?;
+
+    Ok(ProfileTemplate {
+        packages,
+        ..Default::default()
+    })
+}
+
+/// Install a template's packages to a new profile and apply its enabled states
+pub(super) fn apply(template: &ProfileTemplate, prof: &Path, no_cache: bool) -> Result<()> {
+    let mut mods = vec![];
+    for package in &template.packages {
+        match ModName::try_from(package.as_str()) {
+            Ok(name) => mods.push(name),
+            Err(e) => println!("Skipping invalid package '{}': {e}", package.bright_red()),
+        }
+    }
+
+    sync::install_exact(mods, prof, no_cache, true)?;
+
+    if !template.enabled.is_empty() {
+        let changed = sync::apply_states(prof, &template.enabled)?;
+        debug!("Changed the enabled state of {changed} mods");
+    }
+
+    Ok(())
+}
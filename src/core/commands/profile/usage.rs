@@ -0,0 +1,113 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    utils::{file_id, init_msg, link_count, to_file_size_string},
+};
+
+use super::find_profiles;
+
+#[derive(Default)]
+struct Usage {
+    unique: u64,
+    shared: u64,
+}
+
+/// Sum up the size of a directory, counting files hardlinked to other profiles as shared
+///
+/// Links into the Northstar store don't count, a file that's only linked to the store isn't shared
+/// with another profile. Reflinked files can't be told apart from regular files, so they're counted
+/// as unique.
+fn usage(dir: &Path, store: &HashSet<(u64, u64)>, total: &mut Usage) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            usage(&entry.path(), store, total)?;
+            continue;
+        }
+
+        let mut links = link_count(&meta);
+        if file_id(&meta).is_some_and(|id| store.contains(&id)) {
+            links -= 1;
+        }
+        if links > 1 {
+            total.shared += meta.len();
+        } else {
+            total.unique += meta.len();
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the file ids of everything in the Northstar store
+fn store_files(dir: &Path, ids: &mut HashSet<(u64, u64)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            store_files(&entry.path(), ids)?;
+        } else if let Some(id) = file_id(&meta) {
+            ids.insert(id);
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn disk_usage(names: &[String]) -> Result<()> {
+    let Some(game) = CONFIG.game_dir() else {
+        return Err(init_msg());
+    };
+
+    let profiles = if names.is_empty() {
+        find_profiles(game)?
+    } else {
+        let mut profiles = vec![];
+        for name in names {
+            let dir = game.join(name);
+            if !dir.try_exists()? {
+                println!("Profile {} doesn't exist", name.bright_cyan());
+                return Err(anyhow!("Profile not found"));
+            }
+            profiles.push(dir);
+        }
+        profiles
+    };
+
+    let mut store = HashSet::new();
+    let store_dir = DIRS.data_dir().join("northstar");
+    if store_dir.try_exists()? {
+        store_files(&store_dir, &mut store)?;
+    }
+
+    println!(
+        "{:<24}{:>12}{:>12}{:>12}",
+        "Profile".bold(),
+        "Unique".bold(),
+        "Shared".bold(),
+        "Total".bold()
+    );
+    for dir in profiles {
+        let mut total = Usage::default();
+        usage(&dir, &store, &mut total)?;
+
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<24}{:>12}{:>12}{:>12}",
+            name.bright_cyan(),
+            to_file_size_string(total.unique),
+            to_file_size_string(total.shared).bright_green(),
+            to_file_size_string(total.unique + total.shared)
+        );
+    }
+
+    Ok(())
+}
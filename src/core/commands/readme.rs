@@ -0,0 +1,377 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{ErrorKind, IsTerminal, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    model::{Cache, ModName, PackageIndex},
+    traits::Index,
+    utils::{ensure_dir, find_package_roots, read_zip_text},
+};
+
+pub fn readme(name: ModName, no_pager: bool) -> Result<()> {
+    let text = match find_readme(&name)? {
+        Some(text) => render(&text, textwrap::termwidth().min(100)),
+        None => {
+            let index = PackageIndex::fetch()?;
+            let Some(m) = index.get_item(&name) else {
+                println!("Couldn't find package {}", name.bright_cyan());
+                return Err(anyhow!("Package not found"));
+            };
+            let version = name
+                .version
+                .as_ref()
+                .and_then(|v| m.get_version(v.to_string()))
+                .or_else(|| m.get_latest())
+                .ok_or_else(|| anyhow!("Package {name} has no versions"))?;
+
+            println!(
+                "{} has no README, showing its description instead\n",
+                name.bright_cyan()
+            );
+            format!("{}\n", textwrap::fill(&version.desc, textwrap::termwidth()))
+        }
+    };
+
+    if no_pager {
+        print!("{text}");
+        Ok(())
+    } else {
+        page(&text)
+    }
+}
+
+/// Look for a README in the installed package, then in the cached zip
+fn find_readme(name: &ModName) -> Result<Option<String>> {
+    if let Ok(dir) = CONFIG.install_dir()
+        && dir.try_exists()?
+    {
+        for root in find_package_roots(dir)? {
+            let Ok(installed) = ModName::try_from(root.as_path()) else {
+                continue;
+            };
+            if installed.author.eq_ignore_ascii_case(&name.author)
+                && installed.name.eq_ignore_ascii_case(&name.name)
+                && name
+                    .version
+                    .as_ref()
+                    .is_none_or(|v| installed.version.as_ref() == Some(v))
+                && let Some(text) = read_dir_readme(&root)?
+            {
+                debug!("Using README from '{}'", root.display());
+                return Ok(Some(text));
+            }
+        }
+    }
+
+    ensure_dir(DIRS.cache_dir())?;
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+    let cached = match &name.version {
+        Some(_) => cache.get(name),
+        None => cache
+            .get_all(name)
+            .into_iter()
+            .max_by(|(a, _), (b, _)| a.version.cmp(&b.version))
+            .map(|(_, path)| path),
+    };
+    if let Some(path) = cached {
+        debug!("Using README from '{}'", path.display());
+        return read_zip_text(File::open(path)?, "README.md");
+    }
+
+    Ok(None)
+}
+
+fn read_dir_readme(dir: &Path) -> Result<Option<String>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|n| n.eq_ignore_ascii_case("README.md"))
+        {
+            return Ok(Some(fs::read_to_string(path)?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Send text through the user's pager if stdout is a terminal
+fn page(text: &str) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        print!("{text}");
+        return Ok(());
+    }
+
+    let pager = env::var("PAPA_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| {
+            if cfg!(windows) {
+                "more".into()
+            } else {
+                // keep colors, and don't page text that fits on one screen
+                "less -RFX".into()
+            }
+        });
+    let mut parts = pager.split_whitespace();
+    let Some(cmd) = parts.next() else {
+        print!("{text}");
+        return Ok(());
+    };
+
+    let mut child = match Command::new(cmd).args(parts).stdin(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("Unable to start pager '{pager}': {e}");
+            print!("{text}");
+            return Ok(());
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = stdin.write_all(text.as_bytes())
+        && e.kind() != ErrorKind::BrokenPipe
+    {
+        return Err(e.into());
+    }
+    child.wait()?;
+
+    Ok(())
+}
+
+/// Render Markdown as styled, wrapped text for the terminal
+fn render(markdown: &str, width: usize) -> String {
+    let mut r = Renderer {
+        width,
+        ..Default::default()
+    };
+
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(markdown, options) {
+        r.event(event);
+    }
+    r.flush();
+
+    r.out.trim_end().to_string() + "\n"
+}
+
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    width: usize,
+    /// Inline text waiting to be wrapped
+    line: String,
+    /// Prefixes for block quotes and list item continuation lines
+    prefixes: Vec<String>,
+    /// The marker for a list item that hasn't printed its first line yet
+    marker: Option<String>,
+    /// The next number of each ordered list, or `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    heading: Option<HeadingLevel>,
+    code: Option<String>,
+    links: Vec<String>,
+    strong: usize,
+    emphasis: usize,
+    strike: usize,
+    image: bool,
+    table_row: usize,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some(code) = &mut self.code {
+                    code.push_str(&text);
+                } else {
+                    let styled = self.styled(&text);
+                    self.line.push_str(&styled);
+                }
+            }
+            Event::Code(code) => self.line.push_str(&code.bright_yellow().to_string()),
+            Event::SoftBreak => self.line.push(' '),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.out
+                    .push_str(&"─".repeat(self.width).dimmed().to_string());
+                self.out.push_str("\n\n");
+            }
+            Event::TaskListMarker(done) => self.line.push_str(if done { "[x] " } else { "[ ] " }),
+            // raw HTML doesn't mean anything in a terminal
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.heading = Some(level);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.prefixes.push("│ ".dimmed().to_string());
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.code = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.prefixes.push(" ".repeat(marker.chars().count()));
+                self.marker = Some(marker);
+            }
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strong => self.strong += 1,
+            Tag::Strikethrough => self.strike += 1,
+            Tag::Link { dest_url, .. } => self.links.push(dest_url.to_string()),
+            Tag::Image { .. } => {
+                self.image = true;
+                self.line.push_str(&"[image: ".dimmed().to_string());
+            }
+            Tag::TableRow | Tag::TableHead => self.table_row = 0,
+            Tag::TableCell => {
+                if self.table_row > 0 {
+                    self.line.push_str(&" │ ".dimmed().to_string());
+                }
+                self.table_row += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.heading = None;
+                self.out.push('\n');
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.prefixes.pop();
+            }
+            TagEnd::CodeBlock => {
+                let code = self.code.take().unwrap_or_default();
+                let prefix = self.prefixes.concat();
+                for line in code.trim_end().lines() {
+                    self.out
+                        .push_str(&format!("{prefix}    {}\n", line.bright_yellow()));
+                }
+                self.out.push('\n');
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.prefixes.pop();
+                self.marker = None;
+            }
+            TagEnd::Emphasis => self.emphasis -= 1,
+            TagEnd::Strong => self.strong -= 1,
+            TagEnd::Strikethrough => self.strike -= 1,
+            TagEnd::Link => {
+                if let Some(url) = self.links.pop()
+                    && !url.is_empty()
+                    && !self.image
+                {
+                    self.line
+                        .push_str(&format!(" <{}>", url.bright_blue().underline()));
+                }
+            }
+            TagEnd::Image => {
+                self.image = false;
+                self.line.push_str(&"]".dimmed().to_string());
+            }
+            TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Table => {
+                self.flush();
+                self.out.push('\n');
+            }
+            _ => {}
+        }
+    }
+
+    fn styled(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.strong > 0 {
+            text = text.bold().to_string();
+        }
+        if self.emphasis > 0 {
+            text = text.italic().to_string();
+        }
+        if self.strike > 0 {
+            text = text.strikethrough().to_string();
+        }
+        if self.image {
+            text = text.dimmed().to_string();
+        }
+
+        text
+    }
+
+    /// Wrap and write out any pending inline text
+    fn flush(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        if line.trim().is_empty() {
+            return;
+        }
+
+        let rest = self.prefixes.concat();
+        let first = match self.marker.take() {
+            Some(marker) => {
+                let outer = &self.prefixes[..self.prefixes.len().saturating_sub(1)];
+                format!("{}{marker}", outer.concat())
+            }
+            None => rest.clone(),
+        };
+
+        let line = match self.heading {
+            Some(HeadingLevel::H1) => line.bright_cyan().bold().underline().to_string(),
+            Some(HeadingLevel::H2) => line.bright_cyan().bold().to_string(),
+            Some(_) => line.bold().to_string(),
+            None => line,
+        };
+
+        let opts = textwrap::Options::new(self.width)
+            .initial_indent(&first)
+            .subsequent_indent(&rest);
+        self.out.push_str(&textwrap::fill(line.trim(), opts));
+        self.out.push('\n');
+    }
+}
@@ -1,29 +1,127 @@
-use std::fs;
-
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use owo_colors::OwoColorize;
+use thermite::CORE_MODS;
 use tracing::debug;
 
-use crate::{config::CONFIG, model::ModName, utils::find_package_roots};
+use crate::{
+    config::CONFIG,
+    core::trash::trash,
+    get_answer,
+    model::ModName,
+    traits::Answer,
+    utils::{did_you_mean, find_packages, find_unmanaged, suggest, validate_modname},
+};
 
-pub fn remove(mods: Vec<ModName>) -> Result<()> {
-    let locals = find_package_roots(CONFIG.install_dir()?)?;
+pub fn remove(mods: Vec<String>, unmanaged: bool, yes: bool) -> Result<()> {
+    if unmanaged {
+        return remove_unmanaged(mods, yes);
+    }
 
-    for m in mods {
-        debug!("Searching for '{m}'");
-        if let Some(installed) = locals.iter().find(|v| {
-            let Ok(local) = ModName::try_from(v.as_path()) else {
-                return false;
-            };
+    let mods = mods
+        .iter()
+        .map(|m| validate_modname(m))
+        .collect::<Result<Vec<_>>>()?;
+    let locals = find_packages(CONFIG.install_dir()?)?;
+    let names = locals
+        .iter()
+        .map(|(local, _)| format!("{}.{}", local.author, local.name))
+        .collect::<Vec<_>>();
 
+    let find = |m: &ModName| {
+        locals.iter().find(|(local, _)| {
             debug!("Testing '{local}'");
             m.name.to_lowercase() == local.name.to_lowercase()
                 && m.author.to_lowercase() == local.author.to_lowercase()
-        }) {
-            println!("Removing package '{}'", m.bright_cyan());
-            debug!("Removing mod {installed:?}");
-            fs::remove_dir_all(installed)?;
+        })
+    };
+
+    let mut missing = false;
+    for m in mods {
+        debug!("Searching for '{m}'");
+        let (name, path) = match find(&m) {
+            Some(found) => found,
+            None => {
+                println!("Couldn't find package {}", m.bright_cyan());
+                let suggestions = suggest(
+                    &format!("{}.{}", m.author, m.name),
+                    names.iter().map(String::as_str),
+                );
+                let Some(found) = did_you_mean(&suggestions, yes)?
+                    .and_then(|name| find(&ModName::try_from(name).ok()?))
+                else {
+                    missing = true;
+                    continue;
+                };
+                found
+            }
+        };
+
+        println!("Removing package '{}'", name.bright_cyan());
+        debug!("Removing mod {path:?}");
+        let id = trash(path, format!("remove {name}"))?;
+        println!(
+            "Moved to the trash, run '{}' to undo",
+            format!("papa trash restore {id}").bright_cyan()
+        );
+    }
+
+    if missing {
+        return Err(anyhow!("Failed to find some packages"));
+    }
+
+    Ok(())
+}
+
+/// Remove mods from the legacy `mods` folder by their name or folder name
+fn remove_unmanaged(mods: Vec<String>, yes: bool) -> Result<()> {
+    let unmanaged = find_unmanaged(CONFIG.install_dir()?)?;
+
+    let find = |m: &str| {
+        unmanaged.iter().find(|(path, json)| {
+            json.name.eq_ignore_ascii_case(m)
+                || path.file_name().is_some_and(|f| f.eq_ignore_ascii_case(m))
+        })
+    };
+
+    let mut missing = false;
+    for m in mods {
+        let (path, json) = match find(&m) {
+            Some(found) => found,
+            None => {
+                println!("Couldn't find unmanaged mod {}", m.bright_cyan());
+                let suggestions = suggest(&m, unmanaged.iter().map(|(_, json)| json.name.as_str()));
+                let Some(found) = did_you_mean(&suggestions, yes)?.and_then(find) else {
+                    missing = true;
+                    continue;
+                };
+                found
+            }
+        };
+
+        if CORE_MODS.contains(&json.name.to_lowercase().as_str())
+            || json.name.to_lowercase().starts_with("northstar.")
+        {
+            println!(
+                "{} is a Northstar core mod, Northstar won't work without it until you run '{}'",
+                json.name.bright_red(),
+                "papa ns init -f".bright_cyan()
+            );
+            let ans = get_answer!(yes, "Remove it anyway? [y/N]: ")?;
+            if !ans.is_yes() {
+                continue;
+            }
         }
+
+        println!("Removing unmanaged mod '{}'", json.name.bright_cyan());
+        let id = trash(path, format!("remove --unmanaged {}", json.name))?;
+        println!(
+            "Moved to the trash, run '{}' to undo",
+            format!("papa trash restore {id}").bright_cyan()
+        );
+    }
+
+    if missing {
+        return Err(anyhow!("Failed to find some mods"));
     }
 
     Ok(())
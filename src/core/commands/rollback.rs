@@ -0,0 +1,129 @@
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use semver::Version;
+use tracing::debug;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    get_answer,
+    model::{Cache, ModName, PackageIndex},
+    traits::{Answer, Index},
+    utils::{download_and_replace, ensure_dir, find_packages, init_msg},
+};
+
+use super::{history, profile::ProfileMeta};
+
+pub fn rollback(name: ModName, pin: bool, yes: bool, no_cache: bool) -> Result<()> {
+    let Some(profile) = CONFIG.current_profile_dir() else {
+        return Err(init_msg());
+    };
+    let install_dir = CONFIG.install_dir()?;
+
+    let Some((installed, path)) = find_packages(&install_dir)?.into_iter().find(|(m, _)| {
+        m.author.to_lowercase() == name.author.to_lowercase()
+            && m.name.to_lowercase() == name.name.to_lowercase()
+    }) else {
+        println!("Package {} isn't installed", name.bright_cyan());
+        return Err(anyhow!("Package not installed"));
+    };
+    let key = ModName::new(&installed.author, &installed.name, None);
+    let Some(current) = installed.version.clone() else {
+        return Err(anyhow!(
+            "Unable to tell which version of {key} is installed"
+        ));
+    };
+
+    let target = match name.version {
+        Some(version) => version,
+        None => match previous_version(&key, &current)? {
+            Some(version) => version,
+            None => {
+                println!(
+                    "No version of {} older than {} is known, specify one with {}",
+                    key.bright_cyan(),
+                    format!("v{current}").bright_cyan(),
+                    format!("{key}@<VERSION>").bright_cyan()
+                );
+                return Err(anyhow!("No previous version found"));
+            }
+        },
+    };
+
+    if target == current {
+        println!(
+            "{} is already at {}",
+            key.bright_cyan(),
+            format!("v{current}").bright_cyan()
+        );
+        return Ok(());
+    }
+
+    let index = PackageIndex::fetch()?;
+    let Some(version) = index
+        .get_item(&key)
+        .and_then(|m| m.get_version(target.to_string()))
+    else {
+        println!(
+            "Package {} has no version {}",
+            key.bright_cyan(),
+            target.bright_cyan()
+        );
+        return Err(anyhow!("Version not found"));
+    };
+
+    let ans = get_answer!(
+        yes,
+        "Roll back {} from {} to {}? [Y/n]: ",
+        key.bright_cyan(),
+        format!("v{current}").bright_red(),
+        format!("v{target}").bright_green()
+    )?;
+    if ans.is_no() {
+        return Ok(());
+    }
+
+    let target_name = ModName::new(&key.author, &key.name, Some(target.clone()));
+    download_and_replace(
+        vec![(target_name, version)],
+        vec![(path, format!("rollback {key}"))],
+        !no_cache,
+        |_| Ok(()),
+    )?;
+
+    if pin {
+        let mut meta = ProfileMeta::load(&profile)?;
+        meta.pinned.insert(key.to_string());
+        meta.save(&profile)?;
+        println!("Pinned {}, 'papa update' will skip it", key.bright_cyan());
+    }
+
+    println!(
+        "Rolled back {} to {}",
+        key.bright_cyan(),
+        format!("v{target}").bright_green()
+    );
+
+    Ok(())
+}
+
+/// Find the version a package was at before it was last changed to `current`
+///
+/// Falls back to the newest cached version older than `current` if the history doesn't know.
+fn previous_version(name: &ModName, current: &Version) -> Result<Option<Version>> {
+    if let Some(version) =
+        history::previous_version(CONFIG.current_profile(), &name.to_string(), current)?
+    {
+        debug!("Found previous version {version} of {name} in the history");
+        return Ok(Some(version));
+    }
+
+    ensure_dir(DIRS.cache_dir())?;
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+
+    Ok(cache
+        .get_all(name)
+        .into_iter()
+        .filter_map(|(cached, _)| cached.version.clone())
+        .filter(|v| v < current)
+        .max())
+}
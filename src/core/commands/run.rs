@@ -7,6 +7,7 @@ use thermite::TITANFALL2_STEAM_ID;
 
 use crate::config::CONFIG;
 use crate::config::InstallType::*;
+use crate::core::profile::ProfileMeta;
 
 #[derive(Args)]
 pub struct RunOptions {
@@ -21,6 +22,8 @@ pub struct RunOptions {
     vanilla: bool,
 
     ///Extra args to pass to the game
+    ///
+    ///Defaults to the run args set for the current profile, if any
     extra: Vec<String>,
 }
 
@@ -31,6 +34,15 @@ pub fn run(
         extra,
     }: RunOptions,
 ) -> Result<()> {
+    let extra = if extra.is_empty()
+        && !no_profile
+        && let Some(dir) = CONFIG.current_profile_dir()
+    {
+        ProfileMeta::load(dir)?.run_args
+    } else {
+        extra
+    };
+
     match CONFIG.install_type() {
         Steam(t) => {
             println!("Launching Titanfall 2 using steam...");
@@ -1,12 +1,12 @@
+use crate::model::PackageIndex;
 use crate::traits::Index;
 use anyhow::Result;
 use owo_colors::OwoColorize;
 use textwrap::Options;
-use thermite::prelude::*;
 use tracing::debug;
 
 pub fn search(term: &[String]) -> Result<()> {
-    let index = get_package_index()?;
+    let index = PackageIndex::fetch()?;
     let term = term.join("");
     debug!("Searching for term '{}'", term.bold());
 
@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use clap::Subcommand;
+use indicatif::ProgressBar;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime, macros::format_description};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{CONFIG, DIRS},
+    get_answer,
+    traits::Answer,
+    utils::{CopyMode, copy_dir_with_progress, dir_size, ensure_dir, to_file_size_string},
+};
+
+const ENTRY_FILE: &str = "entry.ron";
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    #[clap(alias("ls"))]
+    ///List everything in the trash
+    List,
+    ///Move an item from the trash back to where it was deleted from
+    Restore {
+        ///ID of the item, as shown by 'papa trash list'
+        id: String,
+        ///Replace anything that now exists at the original location
+        #[arg(long, short)]
+        force: bool,
+    },
+    ///Permanently delete everything in the trash
+    Empty {
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+/// Record of a single file or folder that was moved to the trash
+#[derive(Serialize, Deserialize)]
+struct TrashEntry {
+    /// Where the item was deleted from
+    original: PathBuf,
+    /// The operation that deleted it
+    operation: String,
+    #[serde(with = "time::serde::rfc3339")]
+    deleted: OffsetDateTime,
+}
+
+pub fn handle(command: &TrashCommands) -> Result<()> {
+    match command {
+        TrashCommands::List => list(),
+        TrashCommands::Restore { id, force } => restore(id, *force),
+        TrashCommands::Empty { yes } => empty(*yes),
+    }
+}
+
+fn trash_dir() -> PathBuf {
+    DIRS.data_dir().join("trash")
+}
+
+/// Move a file or folder to the trash instead of deleting it, returning its ID in the trash
+///
+/// `operation` describes what deleted the item, e.g. "remove Author.Mod"
+pub fn trash(path: impl AsRef<Path>, operation: impl Into<String>) -> Result<String> {
+    let path = path.as_ref();
+    let dir = trash_dir();
+    ensure_dir(&dir)?;
+    // a trash that can't be cleaned up shouldn't stop anything from being deleted
+    if let Err(e) = expire() {
+        warn!("Unable to remove expired items from the trash: {e}");
+    }
+
+    let deleted = OffsetDateTime::now_utc();
+    let stamp = deleted.format(format_description!(
+        "[year][month][day]-[hour][minute][second]"
+    ))?;
+    let mut n = 0;
+    let (id, entry_dir) = loop {
+        let id = format!("{stamp}-{n}");
+        let entry_dir = dir.join(&id);
+        if !entry_dir.try_exists()? {
+            break (id, entry_dir);
+        }
+        n += 1;
+    };
+
+    let Some(name) = path.file_name() else {
+        return Err(anyhow!("Can't move '{}' to the trash", path.display()));
+    };
+    let entry = TrashEntry {
+        original: path.canonicalize()?,
+        operation: operation.into(),
+        deleted,
+    };
+
+    fs::create_dir(&entry_dir)?;
+    move_item(path, &entry_dir.join(name))?;
+    fs::write(
+        entry_dir.join(ENTRY_FILE),
+        ron::ser::to_string_pretty(&entry, Default::default())?,
+    )?;
+
+    debug!("Moved '{}' to the trash as {id}", path.display());
+
+    Ok(id)
+}
+
+/// Rename an item, falling back to copying it when the destination is on another filesystem
+pub(crate) fn move_item(from: &Path, to: &Path) -> Result<()> {
+    if let Err(e) = fs::rename(from, to) {
+        debug!(
+            "Unable to rename '{}', copying instead: {e}",
+            from.display()
+        );
+        if from.is_dir() {
+            let size = dir_size(from).unwrap_or_default();
+            warn!(
+                "'{}' is on another drive, so {} has to be copied into '{}'",
+                from.display(),
+                to_file_size_string(size),
+                to.display()
+            );
+            copy_dir_with_progress(from, to, CopyMode::Copy, &ProgressBar::hidden())?;
+            fs::remove_dir_all(from)?;
+        } else {
+            fs::copy(from, to)?;
+            fs::remove_file(from)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn entries() -> Result<Vec<(String, TrashEntry)>> {
+    let dir = trash_dir();
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let raw = match fs::read_to_string(path.join(ENTRY_FILE)) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        match ron::from_str(&raw) {
+            Ok(entry) => entries.push((
+                path.file_name()
+                    .expect("trash entry name")
+                    .to_string_lossy()
+                    .to_string(),
+                entry,
+            )),
+            Err(e) => warn!("Unable to read trash entry '{}': {e}", path.display()),
+        }
+    }
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(entries)
+}
+
+/// Permanently delete anything that has been in the trash longer than the configured expiry
+fn expire() -> Result<()> {
+    let Some(days) = CONFIG.trash_expiry() else {
+        return Ok(());
+    };
+
+    let cutoff = OffsetDateTime::now_utc() - Duration::days(days as i64);
+    for (id, entry) in entries()? {
+        if entry.deleted < cutoff {
+            debug!("Trash entry {id} has expired");
+            if let Err(e) = fs::remove_dir_all(trash_dir().join(&id)) {
+                warn!("Unable to remove expired trash entry {id}: {e}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let entries = entries()?;
+    if entries.is_empty() {
+        println!("The trash is empty");
+        return Ok(());
+    }
+
+    let fmt = format_description!("[year]-[month]-[day] [hour]:[minute]");
+    for (id, entry) in entries {
+        let size = dir_size(trash_dir().join(&id)).unwrap_or_default();
+        println!(
+            "{} {} ({})",
+            id.bright_cyan(),
+            entry.original.display(),
+            to_file_size_string(size)
+        );
+        println!(
+            "    {} on {}",
+            entry.operation.bright_purple(),
+            entry.deleted.format(fmt)?
+        );
+    }
+
+    Ok(())
+}
+
+fn restore(id: &str, force: bool) -> Result<()> {
+    let Some((_, entry)) = entries()?.into_iter().find(|(i, _)| i == id) else {
+        println!("Nothing in the trash with ID {}", id.bright_cyan());
+        return Err(anyhow!("Trash entry not found"));
+    };
+
+    let entry_dir = trash_dir().join(id);
+    let name = entry
+        .original
+        .file_name()
+        .ok_or_else(|| anyhow!("Trash entry {id} has no file name"))?;
+
+    if entry.original.try_exists()? {
+        if !force {
+            println!(
+                "'{}' already exists, use {} to replace it",
+                entry.original.display().bright_cyan(),
+                "--force".bright_cyan()
+            );
+            return Err(anyhow!("Restore target already exists"));
+        }
+
+        // keep whatever was in the way so restoring can't lose anything either
+        trash(&entry.original, format!("restore {id}"))?;
+    }
+
+    if let Some(parent) = entry.original.parent() {
+        ensure_dir(parent)?;
+    }
+    move_item(&entry_dir.join(name), &entry.original)?;
+    fs::remove_dir_all(entry_dir)?;
+
+    println!("Restored '{}'", entry.original.display().bright_cyan());
+
+    Ok(())
+}
+
+fn empty(yes: bool) -> Result<()> {
+    let dir = trash_dir();
+    if !dir.try_exists()? {
+        println!("The trash is empty");
+        return Ok(());
+    }
+
+    let size = dir_size(&dir)?;
+    let ans = get_answer!(
+        yes,
+        "Permanently delete {} of files? [y/N]: ",
+        to_file_size_string(size)
+    )?;
+    if !yes && !ans.is_yes() {
+        println!("Not emptying the trash");
+        return Ok(());
+    }
+
+    fs::remove_dir_all(&dir)?;
+    println!("Emptied the trash");
+
+    Ok(())
+}
@@ -1,26 +1,57 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    ops::Bound,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     config::CONFIG,
-    core::commands::northstar,
+    core::commands::{changelog, northstar, verify::diff_package},
+    core::profile::ProfileMeta,
     get_answer,
-    model::ModName,
+    model::{ModName, PackageIndex},
     traits::{Answer, Index},
-    utils::{download_and_install, to_file_size_string},
+    utils::{download_and_replace, to_file_size_string},
 };
 use anyhow::Result;
 use owo_colors::OwoColorize;
+use semver::Version;
 use thermite::{
     model::{InstalledMod, ModVersion},
     prelude::*,
 };
-use tracing::{debug, warn};
+use tracing::debug;
+use zip::ZipArchive;
+
+/// A file in an installed package that doesn't match what the package shipped
+struct ModifiedFile {
+    path: String,
+    current: Vec<u8>,
+    shipped: Vec<u8>,
+}
+
+/// What happened to the modified files of the packages that were updated
+#[derive(Default)]
+struct CarryOver {
+    /// Files the update didn't change, so the local copy simply replaced the new one
+    kept: usize,
+    /// Files that were changed both locally and by the update
+    conflicts: Vec<(ModName, String)>,
+    /// Files that the new version no longer has
+    dropped: Vec<(ModName, String)>,
+}
 
-pub fn update(yes: bool, no_cache: bool) -> Result<()> {
+pub fn update(yes: bool, changelog: bool, no_cache: bool) -> Result<()> {
     println!("Checking for outdated packages...");
-    let index = get_package_index()?;
+    let index = PackageIndex::fetch()?;
     let local: Vec<InstalledMod> = find_mods(CONFIG.install_dir()?)?;
     let mut outdated: HashMap<ModName, &ModVersion> = HashMap::new();
+    let meta = match CONFIG.current_profile_dir() {
+        Some(dir) => ProfileMeta::load(dir)?,
+        None => ProfileMeta::default(),
+    };
 
     for l in &local {
         debug!("Checking if mod '{}' is out of date", l.manifest.name);
@@ -31,6 +62,15 @@ pub fn update(yes: bool, no_cache: bool) -> Result<()> {
                 continue;
             }
             debug!("Checking mod {:?}", m);
+            if m.latest != l.manifest.version_number && meta.is_pinned(&m.author, &m.name) {
+                println!(
+                    "Skipping pinned package {}, remove it from '{}' in {} to update it",
+                    ModName::from(m).bright_cyan(),
+                    "pinned".bright_cyan(),
+                    "papa-profile.toml".bright_cyan()
+                );
+                continue;
+            }
             if m.latest != l.manifest.version_number {
                 outdated.insert(m.into(), m.get_latest().expect("Missing latest version"));
             }
@@ -56,29 +96,107 @@ pub fn update(yes: bool, no_cache: bool) -> Result<()> {
     }
     println!("\nTotal download size: {}", filesize.bold());
 
+    // only zips that are already here are read, declining the update shouldn't cost a download
+    let pending = if changelog {
+        show_changelogs(&local, outdated.iter())?
+    } else {
+        vec![]
+    };
+    if !pending.is_empty() {
+        println!(
+            "The changelogs of {} packages will be shown once they're downloaded",
+            pending.len().bold()
+        );
+    }
+
     let answer = get_answer!(yes)?;
 
     if !answer.is_no() {
-        let installed =
-            download_and_install(outdated.clone().into_iter().collect(), !no_cache, false)?;
-
-        //clean any old folders
+        // remember what was changed in the old versions before they're replaced
+        let mut modified = HashMap::new();
         for l in &local {
-            debug!("Checking if {} should be cleaned", ModName::from(l));
-            if !installed.contains(&l.path)
-                && outdated
-                    .keys()
-                    .any(|k| k.author == l.author && k.name == l.manifest.name)
+            let name = ModName::from(l);
+            let key = ModName::new(&l.author, &l.manifest.name, None);
+            if !outdated
+                .keys()
+                .any(|k| k.author == l.author && k.name == l.manifest.name)
             {
-                if let Err(e) = fs::remove_dir_all(&l.path) {
-                    warn!("Unable to remove old mod folder {}", l.path.display());
-                    debug!("{e}")
-                } else {
-                    debug!("Cleaned old folder '{}'", l.path.display());
+                continue;
+            }
+
+            let Some(version) = index
+                .get_item(&name)
+                .and_then(|m| m.get_version(l.manifest.version_number.clone()))
+            else {
+                println!(
+                    "Unable to find {} in the package index, any changes to its files won't be kept",
+                    name.bright_cyan()
+                );
+                continue;
+            };
+            match find_modified(&name, &l.path, version) {
+                Ok(Some(files)) if !files.is_empty() => {
+                    modified.insert(key, files);
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    println!(
+                        "{} isn't cached, so any changes to its files won't be kept",
+                        name.bright_cyan()
+                    );
+                }
+                Err(e) => {
+                    println!(
+                        "Unable to check {} for changed files, they won't be kept: {e}",
+                        name.bright_cyan()
+                    );
                 }
             }
         }
 
+        // the old folders are removed in the same transaction, so a failure leaves them in place
+        let old = local
+            .iter()
+            .filter(|l| {
+                outdated
+                    .keys()
+                    .any(|k| k.author == l.author && k.name == l.manifest.name)
+            })
+            .map(|l| {
+                debug!("Replacing old folder '{}'", l.path.display());
+                (l.path.clone(), format!("update {}", ModName::from(l)))
+            })
+            .collect();
+
+        // the changes are carried over before the new versions are moved into place, so a
+        // failure leaves the old versions as they were
+        let mut carried = CarryOver::default();
+        download_and_replace(
+            outdated.clone().into_iter().collect(),
+            old,
+            !no_cache || changelog,
+            |staged| {
+                for path in staged {
+                    let Ok(name) = ModName::try_from(path.as_path()) else {
+                        continue;
+                    };
+                    let key = ModName::new(&name.author, &name.name, None);
+                    if let Some(files) = modified.remove(&key) {
+                        carry_over(&name, path, files, &mut carried)?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        print_carry_over(&carried);
+
+        if !pending.is_empty() {
+            show_changelogs(
+                &local,
+                outdated.iter().filter(|(name, _)| pending.contains(name)),
+            )?;
+        }
+
         if ns_update {
             ns_prompt()?;
         }
@@ -89,6 +207,139 @@ pub fn update(yes: bool, no_cache: bool) -> Result<()> {
     }
 }
 
+/// Print the changelogs of packages whose new zip is cached, returning the ones that aren't
+fn show_changelogs<'a>(
+    local: &[InstalledMod],
+    outdated: impl Iterator<Item = (&'a ModName, &'a &'a ModVersion)>,
+) -> Result<Vec<ModName>> {
+    let mut pending = vec![];
+    for (name, version) in outdated {
+        let Some(file) = changelog::cached(name, version)? else {
+            pending.push(name.clone());
+            continue;
+        };
+
+        let installed = local
+            .iter()
+            .find(|l| l.author == name.author && l.manifest.name == name.name)
+            .and_then(|l| l.manifest.version_number.parse().ok());
+
+        println!(
+            "\n{} {} -> {}",
+            name.bright_cyan().bold(),
+            installed
+                .as_ref()
+                .map_or("?".to_string(), |v: &Version| format!("v{v}"))
+                .bright_red(),
+            format!("v{}", version.version).bright_green()
+        );
+
+        let from = installed.map_or(Bound::Unbounded, Bound::Excluded);
+        changelog::print_changelog(name, file, &(from, Bound::Unbounded))?;
+    }
+    println!();
+
+    Ok(pending)
+}
+
+/// Find the files in an installed package that differ from what its zip shipped
+///
+/// Only a cached copy of the zip is used, downloading the old version of every package would
+/// double the size of an update. Returns `None` if there's no cached copy.
+fn find_modified(
+    name: &ModName,
+    root: &Path,
+    version: &ModVersion,
+) -> Result<Option<Vec<ModifiedFile>>> {
+    let Some(file) = changelog::cached(name, version)? else {
+        return Ok(None);
+    };
+    let mut zip = ZipArchive::new(file)?;
+    let diff = diff_package(root, &mut zip)?;
+
+    let mut res = vec![];
+    for path in diff.modified {
+        let mut shipped = vec![];
+        zip.by_name(&path)?.read_to_end(&mut shipped)?;
+        res.push(ModifiedFile {
+            current: fs::read(root.join(&path))?,
+            path,
+            shipped,
+        });
+    }
+    debug!("{} files in {name} were modified", res.len());
+
+    Ok(Some(res))
+}
+
+/// Put locally modified files into the freshly installed version of a package
+///
+/// Files the update didn't touch get the local copy. Files that were also changed by the update
+/// keep the local copy too, with the new and old versions from the package next to it as
+/// `.papa-new` and `.papa-old`.
+fn carry_over(
+    name: &ModName,
+    root: &Path,
+    files: Vec<ModifiedFile>,
+    carried: &mut CarryOver,
+) -> Result<()> {
+    let key = ModName::new(&name.author, &name.name, None);
+    for file in files {
+        let dest = root.join(&file.path);
+        let Ok(new) = fs::read(&dest) else {
+            carried.dropped.push((key.clone(), file.path));
+            continue;
+        };
+
+        if new == file.current {
+            continue;
+        }
+        fs::write(&dest, &file.current)?;
+        if new == file.shipped {
+            carried.kept += 1;
+        } else {
+            fs::write(with_suffix(&dest, ".papa-new"), &new)?;
+            fs::write(with_suffix(&dest, ".papa-old"), &file.shipped)?;
+            carried.conflicts.push((key.clone(), file.path));
+        }
+    }
+
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn print_carry_over(carried: &CarryOver) {
+    if carried.kept > 0 {
+        println!("Kept your changes to {} files", carried.kept);
+    }
+
+    if !carried.conflicts.is_empty() {
+        println!(
+            "\nThese files were also changed by the update. Your version was kept, compare it with the new version in '{}' and the original in '{}':",
+            ".papa-new".bright_cyan(),
+            ".papa-old".bright_cyan()
+        );
+        for (name, path) in &carried.conflicts {
+            println!("    {} {path}", name.bright_cyan());
+        }
+    }
+
+    if !carried.dropped.is_empty() {
+        println!(
+            "\nThese files were removed by the update, your changes are in '{}':",
+            "papa trash list".bright_cyan()
+        );
+        for (name, path) in &carried.dropped {
+            println!("    {} {path}", name.bright_cyan());
+        }
+    }
+}
+
 fn ns_prompt() -> Result<()> {
     if !northstar::update_ns()? {
         println!(
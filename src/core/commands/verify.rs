@@ -0,0 +1,354 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use owo_colors::OwoColorize;
+use regex::Regex;
+use tracing::debug;
+use zip::ZipArchive;
+
+use crate::{
+    config::{CONFIG, DIRS},
+    get_answer,
+    model::{Cache, ModName, PackageIndex},
+    traits::{Answer, Index},
+    utils::{download_and_install, download_package, ensure_dir, find_packages},
+};
+
+/// How an installed package differs from the zip it was installed from
+///
+/// Paths are relative to the package root and always use `/` as the separator.
+#[derive(Default, Debug)]
+pub(crate) struct PackageDiff {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl PackageDiff {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+pub fn verify(mod_names: Vec<ModName>, no_cache: bool) -> Result<()> {
+    let results = check_packages(&mod_names, no_cache)?;
+    if results.is_empty() {
+        println!("No packages to verify");
+        return Ok(());
+    }
+
+    let total = results.len();
+    let mut changed = 0;
+    for (name, _, diff) in &results {
+        if !diff.is_clean() {
+            changed += 1;
+            print_diff(name, diff);
+        }
+    }
+
+    if changed == 0 {
+        println!("All {total} packages match what they were installed from");
+        Ok(())
+    } else {
+        println!(
+            "{changed} of {total} packages have been changed, run '{}' to reinstall them",
+            "papa repair".bright_cyan()
+        );
+        Err(anyhow!("Some packages failed verification"))
+    }
+}
+
+pub fn repair(mod_names: Vec<ModName>, keep_config: bool, yes: bool, no_cache: bool) -> Result<()> {
+    let broken: Vec<_> = check_packages(&mod_names, no_cache)?
+        .into_iter()
+        .filter(|(_, _, diff)| !diff.is_clean())
+        .collect();
+    if broken.is_empty() {
+        println!("All packages match what they were installed from, nothing to repair");
+        return Ok(());
+    }
+
+    for (name, _, diff) in &broken {
+        print_diff(name, diff);
+    }
+
+    let ans = get_answer!(
+        yes,
+        "Reinstall {} packages? [Y/n]: ",
+        broken.len().bright_cyan()
+    )?;
+    if ans.is_no() {
+        return Ok(());
+    }
+
+    let index = PackageIndex::fetch()?;
+    for (name, root, diff) in broken {
+        let Some(version) = name
+            .version
+            .as_ref()
+            .and_then(|v| index.get_item(&name)?.get_version(v.to_string()))
+        else {
+            println!(
+                "Unable to find {} in the package index, skipping it",
+                name.bright_cyan()
+            );
+            continue;
+        };
+
+        // user-generated files only survive if they're on the allowlist
+        let kept = if keep_config {
+            read_kept_files(&root, &diff)?
+        } else {
+            vec![]
+        };
+
+        download_and_install(vec![(name.clone(), version)], !no_cache, false)?;
+
+        for (path, contents) in &kept {
+            let dest = root.join(path);
+            if let Some(parent) = dest.parent() {
+                ensure_dir(parent)?;
+            }
+            fs::write(dest, contents)?;
+        }
+        if !kept.is_empty() {
+            println!("Kept {} config files in {}", kept.len(), name.bright_cyan());
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare the selected installed packages, or all of them if none are given, against their zips
+fn check_packages(
+    mod_names: &[ModName],
+    no_cache: bool,
+) -> Result<Vec<(ModName, PathBuf, PackageDiff)>> {
+    let install_dir = CONFIG.install_dir()?;
+    let installed = find_packages(&install_dir)?;
+
+    let mut selected = vec![];
+    if mod_names.is_empty() {
+        selected = installed;
+    } else {
+        for name in mod_names {
+            match installed.iter().find(|(m, _)| {
+                m.author.eq_ignore_ascii_case(&name.author)
+                    && m.name.eq_ignore_ascii_case(&name.name)
+            }) {
+                Some(found) => selected.push(found.clone()),
+                None => {
+                    println!("Package {} isn't installed", name.bright_cyan());
+                    return Err(anyhow!("Package not installed"));
+                }
+            }
+        }
+    }
+
+    ensure_dir(DIRS.cache_dir())?;
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+    let mut index = None;
+    let mut res = vec![];
+    for (name, root) in selected {
+        if name.version.is_none() {
+            println!(
+                "Unable to tell which version of {} is installed, skipping it",
+                name.bright_cyan()
+            );
+            continue;
+        }
+
+        let zip = open_zip(&name, &cache, &mut index, no_cache)?;
+        let diff = diff_package(&root, &mut ZipArchive::new(zip)?)?;
+        debug!("{name}: {diff:?}");
+        res.push((name, root, diff));
+    }
+
+    Ok(res)
+}
+
+/// Open the zip a package was installed from, downloading it again if it isn't cached
+fn open_zip(
+    name: &ModName,
+    cache: &Cache,
+    index: &mut Option<PackageIndex>,
+    no_cache: bool,
+) -> Result<File> {
+    if !no_cache && let Some(path) = cache.get(name) {
+        return Ok(File::open(path)?);
+    }
+
+    if index.is_none() {
+        *index = Some(PackageIndex::fetch()?);
+    }
+    let Some(version) = index.as_ref().and_then(|index| {
+        index
+            .get_item(name)?
+            .get_version(name.version.as_ref()?.to_string())
+    }) else {
+        println!(
+            "{} isn't cached or in the package index, so it can't be checked",
+            name.bright_cyan()
+        );
+        return Err(anyhow!("Package not found"));
+    };
+
+    download_package(cache, name, version)
+}
+
+/// Compare the files in a package root against the zip it was installed from
+pub(crate) fn diff_package(
+    root: &Path,
+    zip: &mut ZipArchive<impl Read + Seek>,
+) -> Result<PackageDiff> {
+    let mut diff = PackageDiff::default();
+    let mut shipped = BTreeSet::new();
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        shipped.insert(name.clone());
+
+        let path = root.join(&name);
+        let Ok(meta) = fs::metadata(&path) else {
+            diff.missing.push(name);
+            continue;
+        };
+        if meta.len() != entry.size() {
+            diff.modified.push(name);
+            continue;
+        }
+
+        let mut expected = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut expected)?;
+        if fs::read(&path)? != expected {
+            diff.modified.push(name);
+        }
+    }
+
+    diff.extra = package_files(root)?
+        .into_iter()
+        .filter(|f| !shipped.contains(f))
+        .collect();
+
+    Ok(diff)
+}
+
+/// List every file in a package, relative to its root
+pub(crate) fn package_files(root: &Path) -> Result<Vec<String>> {
+    let mut res = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                let rel: Vec<_> = rel.iter().map(|c| c.to_string_lossy()).collect();
+                res.push(rel.join("/"));
+            }
+        }
+    }
+    res.sort();
+
+    Ok(res)
+}
+
+fn print_diff(name: &ModName, diff: &PackageDiff) {
+    println!("{} has been changed:", name.bright_cyan());
+    for file in &diff.modified {
+        println!("    {} {file}", "~".bright_yellow());
+    }
+    for file in &diff.missing {
+        println!("    {} {file}", "-".bright_red());
+    }
+    for file in &diff.extra {
+        println!("    {} {file}", "+".bright_green());
+    }
+}
+
+/// Read the modified and added files of a package that match the `repair_keep` allowlist
+fn read_kept_files(root: &Path, diff: &PackageDiff) -> Result<Vec<(String, Vec<u8>)>> {
+    let patterns = CONFIG
+        .repair_keep()
+        .iter()
+        .filter_map(|p| match glob_regex(p) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                println!("Ignoring invalid pattern {}: {e}", p.bright_red());
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut kept = vec![];
+    for file in diff.modified.iter().chain(&diff.extra) {
+        let lower = file.to_lowercase();
+        if patterns.iter().any(|re| re.is_match(&lower)) {
+            debug!("Keeping '{file}'");
+            kept.push((file.clone(), fs::read(root.join(file))?));
+        }
+    }
+
+    Ok(kept)
+}
+
+/// Turn a glob like `**/config/*.json` into a regex matching paths relative to a package root
+///
+/// `*` matches within a single path segment, `**` matches across segments. Matching is
+/// case-insensitive, so paths should be lowercased before they're checked.
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    let pattern = pattern.to_lowercase();
+    let mut re = String::from("^");
+    let mut rest = pattern.as_str();
+    while let Some(c) = rest.chars().next() {
+        if let Some(r) = rest.strip_prefix("**/") {
+            re.push_str("(?:.*/)?");
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("**") {
+            re.push_str(".*");
+            rest = r;
+        } else {
+            match c {
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    re.push('$');
+
+    Regex::new(&re).map_err(|e| anyhow!("{e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        let cfg = glob_regex("**/*.cfg").unwrap();
+        assert!(cfg.is_match("settings.cfg"));
+        assert!(cfg.is_match("mods/foo/settings.cfg"));
+        assert!(!cfg.is_match("settings.cfg.bak"));
+
+        let dir = glob_regex("**/Config/**").unwrap();
+        assert!(dir.is_match("config/a.json"));
+        assert!(dir.is_match("mods/foo/config/nested/b.txt"));
+        assert!(!dir.is_match("mods/configs/a.json"));
+
+        let single = glob_regex("mods/*/mod.json").unwrap();
+        assert!(single.is_match("mods/foo/mod.json"));
+        assert!(!single.is_match("mods/foo/bar/mod.json"));
+    }
+}
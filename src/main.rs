@@ -1,11 +1,11 @@
 #![feature(let_chains)]
 
-use core::{RunOptions, profile::ProfileCommands};
+use core::{RunOptions, preset::PresetCommands, profile::ProfileCommands, trash::TrashCommands};
 use std::{fs, io::IsTerminal, path::PathBuf, process::ExitCode};
 
 use clap::{CommandFactory, Parser, Subcommand, ValueHint};
 use clap_complete::{ArgValueCompleter, CompleteEnv, Shell, env::Shells};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use tracing_subscriber::{
     EnvFilter, Layer, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt,
 };
@@ -21,9 +21,14 @@ pub mod utils;
 mod macros;
 
 use model::ModName;
+use semver::Version;
 use utils::validate_modname;
 
-use crate::{config::DIRS, core::profile};
+use crate::{
+    config::CONFIG,
+    config::DIRS,
+    core::{history, preset, profile, trash},
+};
 
 pub const IGNORED_DIRS: [&str; 8] = [
     "__Installer",
@@ -120,11 +125,18 @@ enum Commands {
     ///Remove a mod or mods from the current mods directory
     #[clap(alias = "r", alias = "rm")]
     Remove {
-        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::installed_mods))]
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::removable_mods))]
         #[clap(help = "Mod name(s) to remove")]
-        #[clap(value_parser = validate_modname)]
         #[clap(required = true)]
-        mod_names: Vec<ModName>,
+        mod_names: Vec<String>,
+
+        ///Remove mods from the legacy 'mods' folder instead of packages
+        #[clap(short, long)]
+        unmanaged: bool,
+
+        ///Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
     },
     ///List installed mods
     #[clap(alias = "l", alias = "ls")]
@@ -138,12 +150,56 @@ enum Commands {
         all: bool,
     },
 
+    ///Go back to the version of a mod that was installed before the current one
+    Rollback {
+        ///Mod to roll back, optionally with the version to go back to
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::installed_mods))]
+        #[clap(value_parser = validate_modname)]
+        mod_name: ModName,
+
+        ///Keep 'papa update' from updating the mod again
+        #[arg(long, short)]
+        pin: bool,
+
+        ///Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
     ///Update currently installed mods
     #[clap(alias = "u")]
     Update {
         ///Don't ask for confirmation
         #[clap(short, long)]
         yes: bool,
+
+        ///Show what changed in each package before updating
+        #[clap(short, long)]
+        changelog: bool,
+    },
+
+    ///Show the README of a package
+    Readme {
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::mod_index))]
+        #[clap(value_parser = validate_modname)]
+        mod_name: ModName,
+
+        ///Print the README directly instead of opening it in a pager
+        #[arg(long)]
+        no_pager: bool,
+    },
+
+    ///Show the changelog of a package
+    Changelog {
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::mod_index))]
+        #[clap(value_parser = validate_modname)]
+        mod_name: ModName,
+
+        ///Versions to show as FROM..TO, or a single version
+        ///
+        ///Defaults to the changes since the installed version
+        #[clap(value_name = "FROM..TO")]
+        range: Option<String>,
     },
     ///Search for a mod
     #[clap(alias = "s")]
@@ -174,6 +230,48 @@ enum Commands {
         all: bool,
     },
 
+    ///Check the current profile for problems
+    Doctor {
+        ///Attempt to fix any problems that are found
+        #[clap(long)]
+        fix: bool,
+
+        ///Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    ///Check installed packages for files that were changed, removed or added since installing them
+    Verify {
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::installed_mods))]
+        #[clap(help = "Packages to check, defaults to all installed packages")]
+        #[clap(value_parser = validate_modname)]
+        mod_names: Vec<ModName>,
+    },
+
+    ///Reinstall packages that have been changed since installing them
+    Repair {
+        #[clap(value_name = "MOD", add = ArgValueCompleter::new(completers::installed_mods))]
+        #[clap(help = "Packages to repair, defaults to all installed packages")]
+        #[clap(value_parser = validate_modname)]
+        mod_names: Vec<ModName>,
+
+        ///Keep changed or added files that match the 'repair_keep' patterns in the config
+        #[arg(long, short)]
+        keep_config: bool,
+
+        ///Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    ///Move mods from the legacy 'mods' folder into managed packages
+    Migrate {
+        ///Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
     ///Commands for managing Northstar itself
     #[cfg(feature = "northstar")]
     #[clap(alias("ns"))]
@@ -195,6 +293,93 @@ enum Commands {
         #[clap(subcommand)]
         command: ProfileCommands,
     },
+
+    ///Save and restore sets of enabled mods for the current profile
+    Preset {
+        #[clap(subcommand)]
+        command: PresetCommands,
+    },
+
+    ///Restore or permanently delete files that papa has removed
+    Trash {
+        #[clap(subcommand)]
+        command: TrashCommands,
+    },
+
+    ///Show the commands that have changed your profiles
+    History {
+        ///Number of entries to show
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+        ///Show every entry
+        #[arg(long, short, conflicts_with = "limit")]
+        all: bool,
+    },
+
+    ///Revert the changes made by a command, reinstalling previous versions of mods
+    Undo {
+        ///ID of the history entry to revert, defaults to the most recent
+        id: Option<usize>,
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+/// How a command should be recorded in the history
+enum Journal {
+    /// Record what the command changes in a profile
+    Track(String),
+    /// Only record that the command was run
+    Command(String),
+}
+
+impl Commands {
+    fn journal(&self) -> Option<Journal> {
+        let current = || CONFIG.current_profile().to_string();
+
+        Some(match self {
+            Commands::Install { .. }
+            | Commands::Remove { .. }
+            | Commands::Rollback { .. }
+            | Commands::Update { .. }
+            | Commands::Import { .. }
+            | Commands::Enable { .. }
+            | Commands::Disable { .. }
+            | Commands::Doctor { fix: true, .. }
+            | Commands::Repair { .. }
+            | Commands::Migrate { .. }
+            | Commands::Preset {
+                command: PresetCommands::Apply { .. },
+            } => Journal::Track(current()),
+            #[cfg(feature = "northstar")]
+            Commands::Northstar { command } => match command {
+                NstarCommands::Use {
+                    profile: Some(profile),
+                    ..
+                } => Journal::Track(profile.clone()),
+                NstarCommands::Versions { .. } => return None,
+                _ => Journal::Track(current()),
+            },
+            Commands::Profile { command } => match command {
+                ProfileCommands::New { name, .. } => {
+                    Journal::Track(name.to_string_lossy().to_string())
+                }
+                ProfileCommands::Clone { source, new, .. } => {
+                    Journal::Track(new.clone().unwrap_or_else(|| format!("{source}-copy")))
+                }
+                ProfileCommands::Delete { name, .. } => Journal::Track(name.clone()),
+                ProfileCommands::Sync { target, .. } => Journal::Track(target.clone()),
+                ProfileCommands::Restore {
+                    name: Some(name), ..
+                } => Journal::Track(name.clone()),
+                ProfileCommands::Select { name } => Journal::Command(name.clone()),
+                ProfileCommands::Rename { new, .. } => Journal::Command(new.clone()),
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
 }
 
 #[derive(Subcommand)]
@@ -217,6 +402,29 @@ pub enum NstarCommands {
     // #[cfg(feature = "launcher")]
     // ///Start the Northstar client
     // Start {},
+    ///Switch a profile to a different version of Northstar
+    Use {
+        ///The version of Northstar to use
+        version: Version,
+
+        ///The profile to change, defaults to the current profile
+        #[arg(long, short, add = ArgValueCompleter::new(completers::profiles))]
+        profile: Option<String>,
+
+        ///Keep the profile on this version when running 'papa ns update'
+        #[arg(long)]
+        pin: bool,
+
+        ///Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    ///List available and cached versions of Northstar, and which profiles use them
+    Versions {
+        ///Show every available version instead of only the most recent ones
+        #[arg(long, short)]
+        all: bool,
+    },
     ///Uninstalls Northstar and all related files
     Reset {
         ///Skip confirmation (MAKE SURE YOU WANT TO DO THIS)
@@ -282,6 +490,11 @@ fn main() -> ExitCode {
 
     debug!("Config: {:#?}", *config::CONFIG);
 
+    let journal = cli.command.journal().and_then(|j| match j {
+        Journal::Track(profile) => history::snapshot(&profile),
+        Journal::Command(profile) => Some(history::untracked(&profile)),
+    });
+
     let res = match cli.command {
         Commands::Complete { shell, init } => {
             if let Some(shell) = shell.or_else(Shell::from_env) {
@@ -344,7 +557,9 @@ fn main() -> ExitCode {
                 Err(anyhow::anyhow!("Unknown shell"))
             }
         }
-        Commands::Update { yes } => core::update(yes, cli.no_cache),
+        Commands::Update { yes, changelog } => core::update(yes, changelog, cli.no_cache),
+        Commands::Readme { mod_name, no_pager } => core::readme(mod_name, no_pager),
+        Commands::Changelog { mod_name, range } => core::changelog(mod_name, range, cli.no_cache),
         Commands::List { global, all } => core::list(global, all),
         Commands::Install {
             file, yes, force, ..
@@ -365,18 +580,46 @@ fn main() -> ExitCode {
         }
         Commands::Enable { mods, all } => core::enable(mods.into_iter().collect(), all),
         Commands::Search { term } => core::search(&term),
-        Commands::Remove { mod_names } => core::remove(mod_names),
+        Commands::Remove {
+            mod_names,
+            unmanaged,
+            yes,
+        } => core::remove(mod_names, unmanaged, yes),
+        Commands::Rollback { mod_name, pin, yes } => {
+            core::rollback(mod_name, pin, yes, cli.no_cache)
+        }
         Commands::Import { file, yes, force } => core::import(file, yes, force, cli.no_cache),
         Commands::Export { file } => core::export(file),
         Commands::Env {} => core::env(),
+        Commands::Doctor { fix, yes } => core::doctor(fix, yes, cli.no_cache),
+        Commands::Verify { mod_names } => core::verify(mod_names, cli.no_cache),
+        Commands::Repair {
+            mod_names,
+            keep_config,
+            yes,
+        } => core::repair(mod_names, keep_config, yes, cli.no_cache),
+        Commands::Migrate { yes } => core::migrate(yes, cli.no_cache),
         // Commands::Clear { full } => clear(&ctx, full),
         #[cfg(feature = "northstar")]
-        Commands::Northstar { command } => core::northstar(&command),
+        Commands::Northstar { command } => core::northstar(&command, cli.no_cache),
         #[cfg(feature = "launcher")]
         Commands::Run { options } => core::run(options),
         Commands::Profile { command } => profile::handle(&command, cli.no_cache),
+        Commands::Preset { command } => preset::handle(&command),
+        Commands::Trash { command } => trash::handle(&command),
+        Commands::History { limit, all } => history::history(limit, all),
+        Commands::Undo { id, yes } => history::undo(id, yes, cli.no_cache),
     };
 
+    if res.is_ok()
+        && let Some(before) = journal
+    {
+        let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+        if let Err(e) = history::record(command, before) {
+            warn!("Unable to record this command in the history: {e}");
+        }
+    }
+
     if let Err(e) = res {
         if cli.debug {
             error!("{:#?}", e);
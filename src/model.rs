@@ -1,16 +1,26 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     fmt::Display,
+    fs::{self, File},
+    io::ErrorKind,
     ops::Deref,
     path::{Path, PathBuf},
 };
 
 use anyhow::{Result, anyhow};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thermite::model::{InstalledMod, Manifest, Mod};
+use time::OffsetDateTime;
 use tracing::{debug, warn};
 
-use crate::utils::validate_modname;
+use crate::{
+    config::DIRS,
+    traits::{Index, SCORE_THRESHOLD},
+    utils::{ensure_dir, fetch_package_index, validate_modname},
+};
 
 #[derive(Clone, Debug)]
 pub struct Package {
@@ -199,6 +209,22 @@ pub struct Cache {
     root: PathBuf,
 }
 
+/// Name of the file that records where each cached package came from
+pub const CACHE_DB: &str = "cache.json";
+
+/// What is known about a single zip in the cache
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub sha256: String,
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub downloaded: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used: OffsetDateTime,
+}
+
 impl Cache {
     pub fn as_cache_path(&self, name: impl AsRef<ModName>) -> PathBuf {
         let name = name.as_ref();
@@ -216,14 +242,22 @@ impl Cache {
             })
             .collect::<Vec<_>>();
 
-        keys.sort_by(|a, b| {
-            a.version
-                .as_ref()
-                .and_then(|av| b.version.as_ref().map(|bv| av.cmp(bv)))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        // versionless names sort first, so the newest version is always last
+        keys.sort_by(|a, b| a.version.cmp(&b.version));
 
-        self.packages.get(keys.first()?)
+        self.packages.get(keys.last()?)
+    }
+
+    /// Get every cached version of a package
+    pub fn get_all(&self, name: impl AsRef<ModName>) -> Vec<(&ModName, &PathBuf)> {
+        let name = name.as_ref();
+        self.packages
+            .iter()
+            .filter(|(k, _)| {
+                k.author.to_lowercase() == name.author.to_lowercase()
+                    && k.name.to_lowercase() == name.name.to_lowercase()
+            })
+            .collect()
     }
 
     #[inline]
@@ -246,7 +280,10 @@ impl Cache {
         let mut rd = path.read_dir()?;
         while let Some(Ok(entry)) = rd.next() {
             // ignore any nested directories in the cache
-            if entry.file_type()?.is_dir() {
+            if entry.file_type()?.is_dir()
+                || entry.file_name() == CACHE_DB
+                || entry.file_name() == INDEX_FILE
+            {
                 continue;
             }
 
@@ -270,4 +307,259 @@ impl Cache {
             root: path.to_owned(),
         })
     }
+
+    /// Every cached package, with the path to its zip
+    pub fn packages(&self) -> &BTreeMap<ModName, PathBuf> {
+        &self.packages
+    }
+
+    /// Read the metadata of every package in the cache, keyed by file name
+    pub fn entries(&self) -> Result<BTreeMap<String, CacheEntry>> {
+        let raw = match fs::read_to_string(self.root.join(CACHE_DB)) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        match serde_json::from_str(&raw) {
+            Ok(entries) => Ok(entries),
+            Err(e) => {
+                warn!("Ignoring unreadable cache database: {e}");
+                Ok(BTreeMap::new())
+            }
+        }
+    }
+
+    fn save_entries(&self, mut entries: BTreeMap<String, CacheEntry>) -> Result<()> {
+        // forget about anything that was deleted behind our back
+        entries.retain(|file, _| self.root.join(file).exists());
+        fs::write(
+            self.root.join(CACHE_DB),
+            serde_json::to_string_pretty(&entries)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Record a freshly downloaded zip in the cache database
+    pub fn record(&self, path: impl AsRef<Path>, url: Option<&str>) -> Result<CacheEntry> {
+        let path = path.as_ref();
+        let file = file_name(path)?;
+
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        let now = OffsetDateTime::now_utc();
+        let entry = CacheEntry {
+            url: url.map(String::from),
+            sha256: format!("{:x}", hasher.finalize()),
+            size,
+            downloaded: now,
+            last_used: now,
+        };
+
+        let mut entries = self.entries()?;
+        entries.insert(file, entry.clone());
+        self.save_entries(entries)?;
+
+        Ok(entry)
+    }
+
+    /// Mark a cached zip as just used, so it's the last to be evicted
+    pub fn touch(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = file_name(path.as_ref())?;
+        let mut entries = self.entries()?;
+        let Some(entry) = entries.get_mut(&file) else {
+            debug!("'{file}' isn't in the cache database");
+            return Ok(());
+        };
+        entry.last_used = OffsetDateTime::now_utc();
+
+        self.save_entries(entries)
+    }
+
+    /// Delete cached zips, removing them from the database
+    pub fn remove(&self, paths: &[&PathBuf]) -> Result<()> {
+        for path in paths {
+            debug!("Evicting '{}' from the cache", path.display());
+            fs::remove_file(path)?;
+        }
+
+        self.save_entries(self.entries()?)
+    }
+}
+
+/// Name of the file in the cache directory that holds the last fetched package index
+pub const INDEX_FILE: &str = "index.json";
+
+/// The remote package index, hashed so packages can be found without scanning the whole list
+pub struct PackageIndex {
+    mods: Vec<Mod>,
+    /// Lowercase author and name of each package, pointing into `mods`
+    lookup: HashMap<(String, String), usize>,
+    /// Lowercase `author.name` of each package, sorted for prefix searches
+    names: Vec<(String, usize)>,
+}
+
+impl PackageIndex {
+    pub fn new(mods: Vec<Mod>) -> Self {
+        let mut lookup = HashMap::with_capacity(mods.len());
+        let mut names = Vec::with_capacity(mods.len());
+        for (i, m) in mods.iter().enumerate() {
+            let author = m.author.to_lowercase();
+            let name = m.name.to_lowercase();
+            names.push((format!("{author}.{name}"), i));
+            lookup.insert((author, name), i);
+        }
+        names.sort_unstable();
+
+        Self {
+            mods,
+            lookup,
+            names,
+        }
+    }
+
+    /// Fetch the index from Thunderstore, keeping a copy in the cache for offline use
+    pub fn fetch() -> Result<Self> {
+        let mods = fetch_package_index()?;
+        if let Err(e) = Self::save(&mods) {
+            debug!("Unable to save the package index: {e}");
+        }
+
+        Ok(Self::new(mods))
+    }
+
+    /// Load the copy of the index saved by the last fetch
+    pub fn cached() -> Result<Self> {
+        let raw = fs::read_to_string(DIRS.cache_dir().join(INDEX_FILE))?;
+        Ok(Self::new(serde_json::from_str(&raw)?))
+    }
+
+    fn save(mods: &[Mod]) -> Result<()> {
+        ensure_dir(DIRS.cache_dir())?;
+        // write to a temporary file first so completions never read half an index
+        let path = DIRS.cache_dir().join(INDEX_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(mods)?)?;
+        fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+
+    /// Find a package by author and name, ignoring case
+    pub fn get(&self, author: &str, name: &str) -> Option<&Mod> {
+        let i = self
+            .lookup
+            .get(&(author.to_lowercase(), name.to_lowercase()))?;
+        self.mods.get(*i)
+    }
+
+    /// Every package whose `author.name` starts with `prefix`, ignoring case, in sorted order
+    pub fn complete(&self, prefix: &str) -> impl Iterator<Item = &Mod> {
+        let prefix = prefix.to_lowercase();
+        let start = self
+            .names
+            .partition_point(|(n, _)| n.as_str() < prefix.as_str());
+        self.names[start..]
+            .iter()
+            .take_while(move |(n, _)| n.starts_with(&prefix))
+            .map(|(_, i)| &self.mods[*i])
+    }
+}
+
+impl Deref for PackageIndex {
+    type Target = Vec<Mod>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mods
+    }
+}
+
+impl Index<Mod> for PackageIndex {
+    fn get_item(&self, name: &ModName) -> Option<&Mod> {
+        self.get(&name.author, &name.name)
+    }
+
+    fn search(&self, term: &str) -> Vec<&Mod> {
+        if term.is_empty() {
+            return self.mods.iter().collect();
+        }
+        let matcher = SkimMatcherV2::default();
+        let mut res = vec![];
+        for v in &self.mods {
+            let author = matcher.fuzzy_indices(&v.author, term);
+            let name = matcher.fuzzy_indices(&v.name, term);
+            let desc = matcher.fuzzy_indices(&v.get_latest().unwrap().desc, term);
+
+            if let Some((score, _)) = author {
+                debug!("author matched with score '{score}'");
+                if score >= SCORE_THRESHOLD {
+                    res.push((score, v));
+                }
+            } else if let Some((score, _)) = name {
+                debug!("name matched with score '{score}'");
+                if score >= SCORE_THRESHOLD {
+                    res.push((score, v));
+                }
+            } else if let Some((score, _)) = desc {
+                debug!("desc matched with score '{score}'");
+                if score >= SCORE_THRESHOLD {
+                    res.push((score, v));
+                }
+            }
+        }
+
+        res.sort_by_key(|v| v.0);
+        res.iter().map(|v| v.1).rev().collect()
+    }
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    Ok(path
+        .file_name()
+        .ok_or_else(|| anyhow!("'{}' isn't a file", path.display()))?
+        .to_string_lossy()
+        .to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn package(author: &str, name: &str) -> Mod {
+        Mod {
+            name: name.into(),
+            author: author.into(),
+            latest: "1.0.0".into(),
+            versions: BTreeMap::new(),
+            installed: false,
+            upgradable: false,
+            global: false,
+        }
+    }
+
+    #[test]
+    fn index_lookups() {
+        let index = PackageIndex::new(vec![
+            package("Fifty", "Server_Utilities"),
+            package("S2Mods", "Spectre"),
+            package("Fifty", "Parkour"),
+        ]);
+
+        assert_eq!(
+            index
+                .get_item(&ModName::new("fifty", "server_utilities", None))
+                .map(|m| m.name.as_str()),
+            Some("Server_Utilities")
+        );
+        assert!(index.get("Fifty", "Spectre").is_none());
+
+        let names: Vec<_> = index.complete("FIFTY.").map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Parkour", "Server_Utilities"]);
+        assert_eq!(index.complete("s2mods.spectre").count(), 1);
+        assert_eq!(index.complete("nobody").count(), 0);
+        assert_eq!(index.complete("").count(), 3);
+    }
 }
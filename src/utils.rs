@@ -1,19 +1,21 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs::{self, File, OpenOptions},
-    io::Seek,
+    io::{Read, Seek, Write},
     path::{Path, PathBuf},
     sync::LazyLock,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
     config::{CONFIG, DIRS},
-    model::{Cache, ModName},
+    get_answer,
+    model::{Cache, ModName, PackageIndex},
     modfile,
-    traits::Index,
+    traits::{Answer, Index, SCORE_THRESHOLD},
 };
 use anyhow::{Context, Result, anyhow};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
 use owo_colors::OwoColorize;
 use regex::Regex;
@@ -21,10 +23,10 @@ use semver::Version;
 use thermite::{
     api::get_package_index,
     core::{find_mods, get_enabled_mods},
-    model::{EnabledMods, InstalledMod, ModVersion},
+    model::{EnabledMods, InstalledMod, Manifest, Mod, ModJSON, ModVersion},
     prelude::{download_with_progress, install_mod},
 };
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 
 static RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^(\S\w+)[\.-](\w+)(?:[@-](\d+\.\d+\.\d+))?$").expect("ModName regex")
@@ -63,6 +65,76 @@ pub(crate) fn to_file_size_string(size: u64) -> String {
     }
 }
 
+/// Parse a size like "500MB", "2 GiB" or "1024" into bytes
+pub(crate) fn parse_file_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("'{input}' doesn't start with a number"))?;
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        unit => return Err(anyhow!("Unknown size unit '{unit}'")),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Find the names closest to one that couldn't be found, best match first
+pub(crate) fn suggest<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut res: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|c| {
+            let score = matcher.fuzzy_match(c, input)?;
+            debug!("'{c}' matched '{input}' with score '{score}'");
+            (score >= SCORE_THRESHOLD).then_some((score, c))
+        })
+        .collect();
+
+    // prefer the shortest name when scores tie, it's closest to what was typed
+    res.sort_by(|l, r| r.0.cmp(&l.0).then(l.1.len().cmp(&r.1.len())));
+    res.into_iter().take(3).map(|(_, c)| c).collect()
+}
+
+/// Print suggestions for a name that couldn't be found and offer to use the best one instead
+///
+/// Nothing is picked when `yes` is set, a guess shouldn't be acted on without asking.
+pub(crate) fn did_you_mean<'a>(suggestions: &[&'a str], yes: bool) -> Result<Option<&'a str>> {
+    let Some((best, rest)) = suggestions.split_first() else {
+        return Ok(None);
+    };
+
+    match rest {
+        [] => println!("Did you mean {}?", best.bright_cyan()),
+        [rest @ .., last] => println!(
+            "Did you mean {}{} or {}?",
+            best.bright_cyan(),
+            rest.iter()
+                .map(|s| format!(", {}", s.bright_cyan()))
+                .collect::<String>(),
+            last.bright_cyan()
+        ),
+    }
+    if yes {
+        return Ok(None);
+    }
+
+    let ans = get_answer!(false, "Use {} instead? [y/N]: ", best.bright_cyan())?;
+    Ok(ans.is_yes().then_some(*best))
+}
+
 pub(crate) fn ensure_dir(dir: impl AsRef<Path>) -> Result<()> {
     let dir = dir.as_ref();
 
@@ -89,11 +161,92 @@ pub fn find_enabled_mods(start: impl AsRef<Path>) -> Option<EnabledMods> {
     }
 }
 
+/// Download the given packages and install them into the current profile
+///
+/// See [`download_and_install_to`] for what `cont` does.
 pub(crate) fn download_and_install(
     mods: Vec<(ModName, impl AsRef<ModVersion>)>,
     check_cache: bool,
     cont: bool,
 ) -> Result<Vec<PathBuf>> {
+    install_packages(
+        mods,
+        CONFIG.install_dir()?,
+        current_profile()?,
+        check_cache,
+        cont,
+        vec![],
+        |_| Ok(()),
+    )
+}
+
+/// Download newer versions of packages into the current profile, removing the old versions in the
+/// same transaction so that either the update or nothing at all happens
+///
+/// `before_commit` gets the staged packages before they're moved into place, so anything it
+/// changes in them is part of the transaction too.
+pub(crate) fn download_and_replace(
+    mods: Vec<(ModName, impl AsRef<ModVersion>)>,
+    old: Vec<(PathBuf, String)>,
+    check_cache: bool,
+    before_commit: impl FnOnce(&[PathBuf]) -> Result<()>,
+) -> Result<Vec<PathBuf>> {
+    install_packages(
+        mods,
+        CONFIG.install_dir()?,
+        current_profile()?,
+        check_cache,
+        false,
+        old,
+        before_commit,
+    )
+}
+
+/// Download the given packages and install them into `profile` rather than the current profile
+///
+/// Without `cont` this is all-or-nothing, the first package that fails to install aborts the
+/// whole transaction. With `cont`, failed packages are skipped and the rest are still installed,
+/// so it should only be used when a partial install is better than none.
+pub(crate) fn download_and_install_to(
+    mods: Vec<(ModName, impl AsRef<ModVersion>)>,
+    profile: impl AsRef<Path>,
+    check_cache: bool,
+    cont: bool,
+) -> Result<Vec<PathBuf>> {
+    let profile = profile.as_ref();
+    install_packages(
+        mods,
+        profile.join("packages"),
+        profile,
+        check_cache,
+        cont,
+        vec![],
+        |_| Ok(()),
+    )
+}
+
+/// The profile the install dir belongs to, which is where `enabledmods.json` is kept
+fn current_profile() -> Result<PathBuf> {
+    if let Some(dir) = CONFIG.current_profile_dir() {
+        return Ok(dir);
+    }
+
+    let install_dir = CONFIG.install_dir()?;
+    Ok(install_dir
+        .parent()
+        .map_or_else(|| install_dir.clone(), Path::to_path_buf))
+}
+
+fn install_packages(
+    mods: Vec<(ModName, impl AsRef<ModVersion>)>,
+    target: impl AsRef<Path>,
+    profile: impl AsRef<Path>,
+    check_cache: bool,
+    cont: bool,
+    old: Vec<(PathBuf, String)>,
+    before_commit: impl FnOnce(&[PathBuf]) -> Result<()>,
+) -> Result<Vec<PathBuf>> {
+    let target = target.as_ref();
     if mods.is_empty() {
         println!("Nothing to do!");
         return Ok(vec![]);
@@ -101,33 +254,41 @@ pub(crate) fn download_and_install(
 
     println!("Downloading packages...");
     let mut files = vec![];
+    let mut dirs = vec![];
     let cache_dir = DIRS.cache_dir();
     ensure_dir(cache_dir)?;
     let cache = Cache::from_dir(cache_dir)?;
 
     for (mn, v) in mods {
+        // look packages up by the version being installed, not whatever version the name carries
+        let mn = ModName::new(&mn.author, &mn.name, v.as_ref().version.parse().ok());
         if check_cache {
             if let Some(path) = cache.get(&mn) {
                 println!("Using cached version of {}", mn.bright_cyan());
+                if let Err(e) = cache.touch(path) {
+                    debug!("Unable to update cache database: {e}");
+                }
                 files.push((mn, v.as_ref().full_name.clone(), modfile!(z, path)?));
                 continue;
             }
+
+            match find_external(&mn, v.as_ref()) {
+                Some(ExternalPackage::Zip(src)) => {
+                    let path = import_external_zip(&cache, &mn, &src)?;
+                    files.push((mn, v.as_ref().full_name.clone(), modfile!(z, path)?));
+                    continue;
+                }
+                Some(ExternalPackage::Dir(src)) => {
+                    println!("Using {} from '{}'", mn.bright_cyan(), src.display());
+                    dirs.push((mn, v.as_ref().full_name.clone(), src));
+                    continue;
+                }
+                None => {}
+            }
         }
         let v = v.as_ref();
-        // flush!()?;
-        let filename = cache.as_cache_path(&mn);
-        let pb = ProgressBar::new(v.file_size)
-            .with_style(
-                ProgressStyle::with_template("{msg}{bar} {bytes}/{total_bytes} {duration}")?
-                    .progress_chars(".. "),
-            )
-            .with_message(format!("Downloading {}", mn.bright_cyan()));
-        let mut file = modfile!(filename)?;
-        download_with_progress(&mut file, &v.url, |delta, _, _| {
-            pb.inc(delta);
-        })
-        .context(format!("Error downloading {}", mn.red()))?;
-        pb.finish();
+        let file =
+            download_package(&cache, &mn, v).map_err(|e| e.context("Nothing was changed"))?;
         files.push((mn, v.full_name.clone(), file));
     }
 
@@ -139,20 +300,32 @@ pub(crate) fn download_and_install(
         .with_prefix("Installing ");
     pb.set_tab_width(1);
     pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_length(files.len() as u64);
+    pb.set_length((files.len() + dirs.len()) as u64);
 
     let mut had_error = false;
 
-    let mut installed = vec![];
+    let mut tx = Transaction::begin(target, profile)?;
+    for (path, reason) in old {
+        tx.retire(path, reason);
+    }
+
+    // packages that are already extracted somewhere else only need copying
+    for (mn, full_name, src) in &dirs {
+        pb.set_message(format!("{}", mn.bright_cyan()));
+        let dest = tx.staging().join(full_name);
+        copy_dir_with_progress(src, &dest, CopyMode::Copy, &ProgressBar::hidden())
+            .with_context(|| format!("Failed to copy {}", mn.red()))?;
+        tx.stage(dest);
+        pb.inc(1);
+        pb.suspend(|| println!("Installed {}", mn.bright_cyan()));
+    }
 
     for (mn, full_name, f) in files.iter().progress_with(pb.clone()) {
         pb.set_message(format!("{}", mn.bright_cyan()));
         if CONFIG.is_server() {
             todo!();
         } else {
-            ensure_dir(CONFIG.install_dir()?)?;
-            let mod_path = CONFIG.install_dir()?;
-            match install_mod(full_name, f, mod_path) {
+            match install_mod(full_name, f, tx.staging()) {
                 Err(e) => {
                     had_error = true;
                     pb.suspend(|| {
@@ -161,18 +334,33 @@ pub(crate) fn download_and_install(
                     });
                     if !cont {
                         pb.finish_and_clear();
-                        println!("Aborted due to error");
-                        return Err(e.into());
+                        tx.rollback();
+                        println!("Aborted due to error, no packages were changed");
+                        return Err(anyhow::Error::from(e)
+                            .context(format!("Failed to install {}", mn.red())));
                     }
                 }
                 Ok(mod_path) => {
                     pb.suspend(|| println!("Installed {}", mn.bright_cyan()));
-                    installed.push(mod_path);
+                    tx.stage(mod_path);
                 }
             }
         }
     }
 
+    if let Err(e) = before_commit(tx.staged()) {
+        pb.finish_and_clear();
+        tx.rollback();
+        println!("Aborted due to error, no packages were changed");
+        return Err(e);
+    }
+    let installed = tx.commit()?;
+
+    // re-read the cache so the packages downloaded above count towards its size
+    if let Err(e) = Cache::from_dir(cache_dir).and_then(|cache| prune_cache(&cache)) {
+        warn!("Unable to prune the package cache: {e}");
+    }
+
     pb.disable_steady_tick();
     pb.set_prefix("");
     pb.set_tab_width(0);
@@ -185,6 +373,515 @@ pub(crate) fn download_and_install(
     Ok(installed)
 }
 
+/// Download `url` into `output` using the download settings from the config
+///
+/// Failed attempts are retried from the start with exponential backoff, so `output` is truncated
+/// before each attempt.
+pub(crate) fn download(output: &mut File, url: &str, pb: &ProgressBar) -> Result<u64> {
+    with_retries(
+        || {
+            output.set_len(0)?;
+            output.rewind()?;
+            pb.set_position(0);
+
+            let mut writer = Throttled::new(&mut *output, CONFIG.download().max_speed());
+            let res = download_with_progress(&mut writer, url, |delta, _, total| {
+                pb.set_length(total);
+                pb.inc(delta);
+            });
+            match res {
+                Ok(size) => {
+                    writer.flush()?;
+                    Ok(size)
+                }
+                // a failure to write the file won't go away by downloading it again
+                Err(e) if writer.failed => Err(Attempt::Fatal(e.into())),
+                Err(e) => Err(Attempt::Retry(e.into())),
+            }
+        },
+        pb,
+    )
+}
+
+/// Fetch the package index from Thunderstore, retrying as configured
+///
+/// Commands should use [`PackageIndex::fetch`], which also keeps a copy for offline use.
+pub(crate) fn fetch_package_index() -> Result<Vec<Mod>> {
+    with_retries(
+        || get_package_index().map_err(|e| Attempt::Retry(e.into())),
+        &ProgressBar::hidden(),
+    )
+    .context("Failed to fetch the package index")
+}
+
+/// Why an attempt at a download failed
+enum Attempt {
+    /// The failure might not happen again, e.g. a dropped connection
+    Retry(anyhow::Error),
+    /// Trying again won't help, e.g. the disk is full
+    Fatal(anyhow::Error),
+}
+
+impl From<std::io::Error> for Attempt {
+    fn from(e: std::io::Error) -> Self {
+        Self::Fatal(e.into())
+    }
+}
+
+/// Run `f` until it succeeds or the configured number of retries is used up, reporting each
+/// failed attempt above `pb`
+fn with_retries<T>(
+    mut f: impl FnMut() -> std::result::Result<T, Attempt>,
+    pb: &ProgressBar,
+) -> Result<T> {
+    let attempts = CONFIG.download().retries + 1;
+    let mut attempt = 1;
+    loop {
+        let err = match f() {
+            Ok(res) => return Ok(res),
+            Err(Attempt::Fatal(e)) => return Err(e),
+            Err(Attempt::Retry(e)) => e,
+        };
+        if attempt >= attempts {
+            return Err(err.context(format!("Download failed on attempt {attempt}/{attempts}")));
+        }
+
+        let wait = Duration::from_secs(1 << (attempt - 1).min(5));
+        pb.suspend(|| {
+            println!(
+                "Attempt {attempt}/{attempts} failed: {}, retrying in {}s",
+                err.bright_red(),
+                wait.as_secs()
+            );
+        });
+        std::thread::sleep(wait);
+        attempt += 1;
+    }
+}
+
+/// Writer that keeps a download under `max_speed` bytes per second and remembers whether writing
+/// to the inner writer failed
+struct Throttled<W> {
+    inner: W,
+    max_speed: Option<u64>,
+    start: Instant,
+    total: u64,
+    failed: bool,
+}
+
+impl<W: Write> Throttled<W> {
+    fn new(inner: W, max_speed: Option<u64>) -> Self {
+        Self {
+            inner,
+            max_speed,
+            start: Instant::now(),
+            total: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<W: Write> Write for Throttled<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf).inspect_err(|_| self.failed = true)?;
+        self.total += n as u64;
+
+        // sleep until the average speed is back under the limit
+        if let Some(max) = self.max_speed.filter(|max| *max > 0) {
+            let expected = Duration::from_secs_f64(self.total as f64 / max as f64);
+            if let Some(ahead) = expected.checked_sub(self.start.elapsed()) {
+                std::thread::sleep(ahead);
+            }
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush().inspect_err(|_| self.failed = true)
+    }
+}
+
+/// A copy of a package found in one of the configured `cache_sources`
+pub(crate) enum ExternalPackage {
+    Zip(PathBuf),
+    /// An extracted package, like the ones in r2modman's cache and profiles
+    Dir(PathBuf),
+}
+
+/// Look for a package in other mod managers' caches and profiles
+///
+/// Each source can be a folder of zips or extracted packages, an r2modman style cache with
+/// `Author-Name/version` folders, a single profile, or a folder of profiles. A copy is only used
+/// if it's the same author, name and version as `version`, and zips have to be the size the index
+/// says they are.
+pub(crate) fn find_external(name: &ModName, version: &ModVersion) -> Option<ExternalPackage> {
+    let name = &ModName::new(&name.author, &name.name, version.version.parse().ok());
+    let version_number = name.version.as_ref()?;
+    let full_name = name.as_modstr().to_string();
+
+    for source in CONFIG.cache_sources() {
+        let mut roots = vec![
+            source.clone(),
+            source.join("packages"),
+            source.join("R2Northstar").join("packages"),
+        ];
+        if let Ok(children) = fs::read_dir(source) {
+            roots.extend(
+                children
+                    .filter_map(|c| c.ok())
+                    .map(|c| c.path().join("R2Northstar").join("packages")),
+            );
+        }
+
+        for root in roots {
+            let zip = root.join(format!("{full_name}.zip"));
+            if zip.is_file() && external_zip_matches(&zip, name, version) {
+                return Some(ExternalPackage::Zip(zip));
+            }
+
+            let by_version = root
+                .join(format!("{}-{}", name.author, name.name))
+                .join(version_number.to_string());
+            for dir in [root.join(&full_name), by_version] {
+                if dir.is_dir()
+                    && author_matches(&dir, name)
+                    && manifest_matches(&dir.join("manifest.json"), name)
+                {
+                    return Some(ExternalPackage::Dir(dir));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn manifest_matches(path: &Path, name: &ModName) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Manifest>(&raw).ok())
+        .is_some_and(|m| is_same_package(&m, name))
+}
+
+fn external_zip_matches(path: &Path, name: &ModName, version: &ModVersion) -> bool {
+    // the index has no hashes, so the size is the best check that this is the published zip
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+    if version.file_size > 0 && size != version.file_size {
+        debug!(
+            "Ignoring external copy of {name} that is {size} bytes instead of {}",
+            version.file_size
+        );
+        return false;
+    }
+
+    File::open(path)
+        .ok()
+        .and_then(|f| read_zip_text(f, "manifest.json").ok().flatten())
+        .and_then(|raw| serde_json::from_str::<Manifest>(&raw).ok())
+        .is_some_and(|m| is_same_package(&m, name))
+}
+
+/// Manifests don't name the author, so check the one recorded next to an extracted package
+///
+/// Folders without a record are only trusted because their path already names the author.
+fn author_matches(dir: &Path, name: &ModName) -> bool {
+    let Ok(author) = fs::read_to_string(dir.join("thunderstore_author.txt")) else {
+        return true;
+    };
+
+    let matches = author.trim().eq_ignore_ascii_case(&name.author);
+    if !matches {
+        debug!(
+            "Ignoring external copy of {name} by {} in '{}'",
+            author.trim(),
+            dir.display()
+        );
+    }
+
+    matches
+}
+
+fn is_same_package(manifest: &Manifest, name: &ModName) -> bool {
+    let matches = manifest.name == name.name
+        && name
+            .version
+            .as_ref()
+            .is_some_and(|v| manifest.version_number == v.to_string());
+    if !matches {
+        debug!(
+            "Ignoring external copy of {name} with manifest for {} v{}",
+            manifest.name, manifest.version_number
+        );
+    }
+
+    matches
+}
+
+/// Copy a zip from an external source into the cache
+pub(crate) fn import_external_zip(cache: &Cache, name: &ModName, src: &Path) -> Result<PathBuf> {
+    println!("Using {} from '{}'", name.bright_cyan(), src.display());
+    let path = cache.as_cache_path(name);
+    // never hardlink, since the cached copy can be overwritten by a later download
+    share_file(src, &path, false)?;
+    if let Err(e) = cache.record(&path, None) {
+        debug!("Unable to update cache database: {e}");
+    }
+
+    Ok(path)
+}
+
+/// Download a package into the cache, showing a progress bar
+pub(crate) fn download_package(cache: &Cache, mn: &ModName, v: &ModVersion) -> Result<File> {
+    let filename = cache.as_cache_path(mn);
+    let pb = ProgressBar::new(v.file_size)
+        .with_style(
+            ProgressStyle::with_template("{msg}{bar} {bytes}/{total_bytes} {duration}")?
+                .progress_chars(".. "),
+        )
+        .with_message(format!("Downloading {}", mn.bright_cyan()));
+    let mut file = modfile!(&filename)?;
+    if let Err(e) = download(&mut file, &v.url, &pb) {
+        pb.abandon();
+        // don't leave a partial download in the cache
+        drop(file);
+        if let Err(e) = fs::remove_file(&filename) {
+            debug!("Unable to remove partial download: {e}");
+        }
+        return Err(e.context(format!("Failed to download {}", mn.red())));
+    }
+    pb.finish();
+
+    if let Err(e) = cache.record(&filename, Some(&v.url)) {
+        debug!("Unable to update cache database: {e}");
+    }
+
+    Ok(file)
+}
+
+/// Read a text file from a package zip, using the match closest to the root
+///
+/// The file name is matched case-insensitively, since packages aren't consistent about it
+pub(crate) fn read_zip_text(file: File, name: &str) -> Result<Option<String>> {
+    let mut zip = zip::ZipArchive::new(file)?;
+    let Some(path) = zip
+        .file_names()
+        .filter(|n| {
+            n.rsplit('/')
+                .next()
+                .is_some_and(|f| f.eq_ignore_ascii_case(name))
+        })
+        .min_by_key(|n| n.matches('/').count())
+        .map(String::from)
+    else {
+        return Ok(None);
+    };
+
+    let mut text = String::new();
+    zip.by_name(&path)?.read_to_string(&mut text)?;
+
+    Ok(Some(text))
+}
+
+/// Installs packages to a staging folder first so that a failed install leaves a profile untouched
+///
+/// Packages are only moved into the target directory by [`Transaction::commit`]. Dropping the
+/// transaction without committing it removes the staged packages and restores `enabledmods.json`.
+pub(crate) struct Transaction {
+    target: PathBuf,
+    staging: PathBuf,
+    replaced: PathBuf,
+    staged: Vec<PathBuf>,
+    retired: Vec<(PathBuf, String)>,
+    enabled_mods: PathBuf,
+    enabled_mods_before: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl Transaction {
+    /// Start installing packages into `target`, restoring the `enabledmods.json` of `profile` if
+    /// the transaction isn't committed
+    pub fn begin(target: impl AsRef<Path>, profile: impl AsRef<Path>) -> Result<Self> {
+        let target = target.as_ref().to_path_buf();
+        ensure_dir(&target)?;
+
+        // keep the staging folders next to the target so moving packages is just a rename
+        let parent = target.parent().unwrap_or(&target);
+        let staging = parent.join(".papa-install-staging");
+        let replaced = parent.join(".papa-install-replaced");
+        for dir in [&staging, &replaced] {
+            if dir.try_exists()? {
+                debug!("Removing leftover folder '{}'", dir.display());
+                fs::remove_dir_all(dir)?;
+            }
+            fs::create_dir(dir)?;
+        }
+
+        let enabled_mods = profile.as_ref().join("enabledmods.json");
+        let enabled_mods_before = match fs::read(&enabled_mods) {
+            Ok(raw) => Some(raw),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            target,
+            staging,
+            replaced,
+            staged: vec![],
+            retired: vec![],
+            enabled_mods,
+            enabled_mods_before,
+            done: false,
+        })
+    }
+
+    /// The folder packages should be installed into
+    pub fn staging(&self) -> &Path {
+        &self.staging
+    }
+
+    /// Mark a package installed into the staging folder as part of this transaction
+    pub fn stage(&mut self, path: impl Into<PathBuf>) {
+        self.staged.push(path.into());
+    }
+
+    /// The packages staged so far, still in the staging folder
+    pub fn staged(&self) -> &[PathBuf] {
+        &self.staged
+    }
+
+    /// Move an installed package out of the target directory when the transaction is committed
+    ///
+    /// The package is sent to the trash with `reason` once everything else is in place.
+    pub fn retire(&mut self, path: impl Into<PathBuf>, reason: impl Into<String>) {
+        self.retired.push((path.into(), reason.into()));
+    }
+
+    /// Move every staged package into the target directory, returning their new paths
+    ///
+    /// If any package can't be moved, every package moved so far is put back the way it was.
+    pub fn commit(mut self) -> Result<Vec<PathBuf>> {
+        let mut moved: Vec<(PathBuf, Option<PathBuf>)> = vec![];
+        let mut retired: Vec<(PathBuf, PathBuf, &str)> = vec![];
+        let res = (|| -> Result<()> {
+            for staged in &self.staged {
+                let name = staged
+                    .file_name()
+                    .ok_or_else(|| anyhow!("Staged package has no name"))?;
+                let dest = self.target.join(name);
+                let backup = if dest.try_exists()? {
+                    let backup = self.replaced.join(name);
+                    fs::rename(&dest, &backup).with_context(|| {
+                        format!("Failed to move aside existing package '{}'", dest.display())
+                    })?;
+                    Some(backup)
+                } else {
+                    None
+                };
+                moved.push((dest.clone(), backup));
+
+                fs::rename(staged, &dest).with_context(|| {
+                    format!("Failed to move package '{}' into place", dest.display())
+                })?;
+            }
+
+            for (old, reason) in &self.retired {
+                // a package reinstalled over itself was already moved aside above
+                if moved.iter().any(|(dest, _)| dest == old) || !old.try_exists()? {
+                    continue;
+                }
+                let name = old
+                    .file_name()
+                    .ok_or_else(|| anyhow!("Retired package has no name"))?;
+                let backup = self.replaced.join(name);
+                fs::rename(old, &backup)
+                    .with_context(|| format!("Failed to remove old package '{}'", old.display()))?;
+                retired.push((old.clone(), backup, reason));
+            }
+
+            Ok(())
+        })();
+
+        if let Err(e) = res {
+            for (old, backup, _) in retired.into_iter().rev() {
+                if let Err(e) = fs::rename(&backup, &old) {
+                    error!("Unable to restore '{}': {e}", old.display());
+                }
+            }
+            for (dest, backup) in moved.into_iter().rev() {
+                if dest.exists()
+                    && let Err(e) = fs::remove_dir_all(&dest)
+                {
+                    error!("Unable to remove '{}': {e}", dest.display());
+                }
+                if let Some(backup) = backup
+                    && let Err(e) = fs::rename(&backup, &dest)
+                {
+                    error!("Unable to restore '{}': {e}", dest.display());
+                }
+            }
+            self.rollback();
+            println!("Aborted due to error, no packages were changed");
+            return Err(e);
+        }
+
+        // packages that were reinstalled over themselves go to the trash instead of vanishing
+        for (dest, backup) in &moved {
+            if let Some(backup) = backup
+                && let Err(e) = crate::core::trash::trash(backup, "reinstall")
+            {
+                debug!("Unable to trash replaced package '{}': {e}", dest.display());
+            }
+        }
+        for (old, backup, reason) in &retired {
+            if let Err(e) = crate::core::trash::trash(backup, *reason) {
+                debug!("Unable to trash old package '{}': {e}", old.display());
+            }
+        }
+
+        self.done = true;
+        self.cleanup();
+
+        Ok(moved.into_iter().map(|(dest, _)| dest).collect())
+    }
+
+    /// Throw away the staged packages and restore `enabledmods.json`
+    pub fn rollback(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+
+        let res = match &self.enabled_mods_before {
+            Some(raw) => fs::write(&self.enabled_mods, raw),
+            None if self.enabled_mods.exists() => fs::remove_file(&self.enabled_mods),
+            None => Ok(()),
+        };
+        if let Err(e) = res {
+            error!("Unable to restore '{}': {e}", self.enabled_mods.display());
+        }
+
+        self.cleanup();
+    }
+
+    fn cleanup(&self) {
+        for dir in [&self.staging, &self.replaced] {
+            if dir.exists()
+                && let Err(e) = fs::remove_dir_all(dir)
+            {
+                debug!("Unable to remove '{}': {e}", dir.display());
+            }
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        self.rollback();
+    }
+}
+
 fn temp_path() -> Result<PathBuf> {
     ensure_dir(std::env::temp_dir())?;
     let path = std::env::temp_dir().join("ns-temp");
@@ -195,7 +892,7 @@ fn temp_path() -> Result<PathBuf> {
 pub fn download_northstar(version: Option<Version>) -> Result<File> {
     let nsname = ModName::new("northstar", "northstar", version);
 
-    let nsmod = get_package_index()?
+    let nsmod = PackageIndex::fetch()?
         .get_item(&nsname)
         .cloned()
         .ok_or_else(|| anyhow!("Package index missing northstar mod"))?;
@@ -217,10 +914,11 @@ pub fn download_northstar(version: Option<Version>) -> Result<File> {
         .read(true)
         .open(temp_path()?)?;
 
-    let mut nsfile = modfile!(DIRS.cache_dir().join(format!(
+    let path = DIRS.cache_dir().join(format!(
         "{}",
         ModName::new(&nsmod.author, &nsmod.name, nsversion.version.parse().ok())
-    )))?;
+    ));
+    let mut nsfile = modfile!(&path)?;
 
     let pb = ProgressBar::new(nsversion.file_size)
         .with_style(
@@ -231,9 +929,7 @@ pub fn download_northstar(version: Option<Version>) -> Result<File> {
             "Downloading Northstar version {}",
             nsversion.version
         ));
-    download_with_progress(&mut tmp, &nsversion.url, |delta, _, _| {
-        pb.inc(delta);
-    })?;
+    download(&mut tmp, &nsversion.url, &pb)?;
     pb.finish();
 
     tmp.rewind()?;
@@ -241,6 +937,11 @@ pub fn download_northstar(version: Option<Version>) -> Result<File> {
 
     nsfile.rewind()?;
 
+    let cache = Cache::from_dir(DIRS.cache_dir())?;
+    if let Err(e) = cache.record(&path, Some(&nsversion.url)) {
+        debug!("Unable to update cache database: {e}");
+    }
+
     Ok(nsfile)
 }
 
@@ -324,6 +1025,357 @@ pub fn find_package_roots(dir: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>>
     Ok(res)
 }
 
+/// Find all mods that live directly in the given directory, e.g. a profile's `mods` folder
+pub fn find_submods(dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, ModJSON)>> {
+    let dir = dir.as_ref();
+
+    let mut res = vec![];
+    if !dir.try_exists()? {
+        return Ok(res);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let child = entry?;
+
+        if !child.file_type()?.is_dir() {
+            continue;
+        }
+
+        let Ok(raw) = fs::read_to_string(child.path().join("mod.json")) else {
+            continue;
+        };
+
+        match serde_json::from_str::<ModJSON>(&raw) {
+            Ok(json) => res.push((child.path(), json)),
+            Err(e) => debug!("Skipping '{}': {e}", child.path().display()),
+        }
+    }
+
+    Ok(res)
+}
+
+/// Find the mods in the legacy `mods` folder next to a profile's `packages` folder
+///
+/// These are still loaded by Northstar, but papa doesn't manage them. Includes the core mods.
+pub fn find_unmanaged(install_dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, ModJSON)>> {
+    let Some(profile) = install_dir.as_ref().parent() else {
+        return Ok(vec![]);
+    };
+
+    find_submods(profile.join("mods"))
+}
+
+/// Get the enabled state of every submod installed to a profile, including those in its `mods` folder
+pub fn submod_states(profile: impl AsRef<Path>) -> Result<BTreeMap<String, bool>> {
+    let profile = profile.as_ref();
+
+    let mut names: Vec<String> = find_submods(profile.join("mods"))?
+        .into_iter()
+        .map(|(_, json)| json.name)
+        .collect();
+
+    let packages = profile.join("packages");
+    if packages.try_exists()? {
+        names.extend(find_mods(packages)?.into_iter().map(|m| m.mod_json.name));
+    }
+
+    let enabled = find_enabled_mods(profile);
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let state = enabled.as_ref().is_none_or(|em| em.is_enabled(&name));
+            (name, state)
+        })
+        .collect())
+}
+
+/// Read the version of Northstar installed to a profile from its `Northstar.Client` mod
+pub fn northstar_version(profile: impl AsRef<Path>) -> Option<Version> {
+    let modfile = fs::read_to_string(
+        profile
+            .as_ref()
+            .join("mods")
+            .join("Northstar.Client")
+            .join("mod.json"),
+    )
+    .ok()?;
+    let json: ModJSON = serde_json::from_str(&modfile).ok()?;
+
+    json.version.parse().ok()
+}
+
+/// Open a cached copy of Northstar, using the newest cached version if none is specified
+pub fn cached_northstar(version: Option<&Version>) -> Result<Option<File>> {
+    let cache_dir = DIRS.cache_dir();
+    ensure_dir(cache_dir)?;
+    let cache = Cache::from_dir(cache_dir)?;
+    let nsname = ModName::new("northstar", "Northstar", version.cloned());
+
+    let path = if version.is_some() {
+        cache.get(nsname)
+    } else {
+        cache.get_any(nsname)
+    };
+
+    if let Some(path) = path
+        && let Err(e) = cache.touch(path)
+    {
+        debug!("Unable to update cache database: {e}");
+    }
+
+    Ok(path.map(File::open).transpose()?)
+}
+
+/// Evict the least recently used packages from the cache until it fits in `cache_max_size`
+///
+/// Packages that are installed to any profile are never evicted.
+pub fn prune_cache(cache: &Cache) -> Result<()> {
+    let Some(max) = CONFIG.cache_max_size() else {
+        return Ok(());
+    };
+
+    let referenced = referenced_packages()?;
+    let entries = cache.entries()?;
+    let mut total = 0;
+    let mut candidates = vec![];
+    for (name, path) in cache.packages() {
+        let meta = fs::metadata(path)?;
+        total += meta.len();
+
+        let key = ModName::new(
+            name.author.to_lowercase(),
+            name.name.to_lowercase(),
+            name.version.clone(),
+        );
+        if referenced.contains(&key) {
+            continue;
+        }
+
+        // zips cached before the database existed fall back to their modification time
+        let last_used = match path
+            .file_name()
+            .and_then(|f| entries.get(f.to_string_lossy().as_ref()))
+        {
+            Some(entry) => entry.last_used,
+            None => meta.modified()?.into(),
+        };
+        candidates.push((last_used, path, meta.len()));
+    }
+
+    if total <= max {
+        return Ok(());
+    }
+
+    candidates.sort_by_key(|(last_used, _, _)| *last_used);
+    let mut evict = vec![];
+    let mut freed = 0;
+    for (_, path, size) in candidates {
+        if total - freed <= max {
+            break;
+        }
+        freed += size;
+        evict.push(path);
+    }
+
+    if evict.is_empty() {
+        debug!("Every cached package is in use, nothing to evict");
+        return Ok(());
+    }
+
+    cache.remove(&evict)?;
+    println!(
+        "Removed {} unused packages from the cache to free {}",
+        evict.len().bold(),
+        to_file_size_string(freed)
+    );
+
+    Ok(())
+}
+
+/// Every package and Northstar version installed to any profile, with lowercase names
+fn referenced_packages() -> Result<BTreeSet<ModName>> {
+    let mut dirs = vec![];
+    if let Some(game) = CONFIG.game_dir() {
+        dirs.extend(crate::core::profile::find_profiles(game)?);
+    }
+
+    let mut res = BTreeSet::new();
+    let mut add = |name: &ModName| {
+        res.insert(ModName::new(
+            name.author.to_lowercase(),
+            name.name.to_lowercase(),
+            name.version.clone(),
+        ));
+    };
+
+    if let Ok(dir) = CONFIG.install_dir() {
+        for (name, _) in find_packages(dir)? {
+            add(&name);
+        }
+    }
+    for dir in dirs {
+        for (name, _) in find_packages(dir.join("packages"))? {
+            add(&name);
+        }
+        if let Some(version) = northstar_version(&dir) {
+            add(&ModName::new("northstar", "northstar", Some(version)));
+        }
+    }
+
+    Ok(res)
+}
+
+/// Get the combined size in bytes of every file in a directory
+pub fn dir_size(dir: impl AsRef<Path>) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            size += dir_size(entry.path())?;
+        } else {
+            size += meta.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Extensions of files that are likely to be edited by users, and so must never be hardlinked
+const CONFIG_EXTENSIONS: [&str; 7] = ["cfg", "json", "txt", "ini", "toml", "yml", "yaml"];
+
+/// How [`copy_dir_with_progress`] copies each file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyMode {
+    /// Always write a full copy
+    Copy,
+    /// Share data through a copy-on-write reflink where the filesystem supports it
+    Reflink,
+    /// Like [`CopyMode::Reflink`], but hardlink files that can't be reflinked
+    ///
+    /// Editing a hardlinked file in place changes it everywhere it's linked, so this has to be
+    /// asked for explicitly.
+    Hardlink,
+}
+
+/// Copy a file, sharing its data with the source where the filesystem allows it
+///
+/// Reflinks are always preferred since they're copy-on-write. Hardlinks are only used when
+/// `hardlink` is set and the file doesn't look like a config file.
+pub fn share_file(src: impl AsRef<Path>, dst: impl AsRef<Path>, hardlink: bool) -> Result<u64> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+
+    if reflink_copy::reflink(src, dst).is_ok() {
+        trace!("Reflinked '{}'", dst.display());
+        return Ok(fs::metadata(dst)?.len());
+    }
+
+    let is_config = src.extension().is_some_and(|ext| {
+        CONFIG_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    });
+    // we can't tell when a file is hardlinked on other platforms, so it wouldn't be safe to unshare later
+    if cfg!(unix) && hardlink && !is_config && fs::hard_link(src, dst).is_ok() {
+        trace!("Hardlinked '{}'", dst.display());
+        return Ok(fs::metadata(dst)?.len());
+    }
+
+    Ok(fs::copy(src, dst)?)
+}
+
+/// Recursively copy a directory, incrementing the progress bar by the number of bytes copied
+pub fn copy_dir_with_progress(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    mode: CopyMode,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let dst = dst.as_ref();
+    fs::create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_with_progress(entry.path(), target, mode, pb)?;
+        } else {
+            pb.inc(match mode {
+                CopyMode::Copy => fs::copy(entry.path(), target)?,
+                CopyMode::Reflink => share_file(entry.path(), target, false)?,
+                CopyMode::Hardlink => share_file(entry.path(), target, true)?,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace any hardlinked files in a directory with private copies so they can be safely overwritten
+pub fn break_links(dir: impl AsRef<Path>) -> Result<()> {
+    let dir = dir.as_ref();
+    if !dir.try_exists()? {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            break_links(&path)?;
+        } else if link_count(&entry.metadata()?) > 1 {
+            debug!("Breaking hardlink for '{}'", path.display());
+            let tmp = path.with_extension("papa-tmp");
+            fs::copy(&path, &tmp)?;
+            fs::rename(&tmp, &path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the number of hardlinks to a file
+#[cfg(unix)]
+pub fn link_count(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.nlink()
+}
+
+/// Get the number of hardlinks to a file
+#[cfg(not(unix))]
+pub fn link_count(_meta: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Get the device and inode of a file, which are shared by all of its hardlinks
+#[cfg(unix)]
+pub fn file_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+/// Get the device and inode of a file, which are shared by all of its hardlinks
+#[cfg(not(unix))]
+pub fn file_id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Find the names and roots of all packages in the given directory
+pub fn find_packages(dir: impl AsRef<Path>) -> Result<Vec<(ModName, PathBuf)>> {
+    let dir = dir.as_ref();
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+
+    let mut res = vec![];
+    for root in find_package_roots(dir)? {
+        match ModName::try_from(root.as_path()) {
+            Ok(name) => res.push((name, root)),
+            Err(e) => debug!("Skipping package '{}': {e}", root.display()),
+        }
+    }
+
+    Ok(res)
+}
+
 #[inline]
 #[must_use]
 pub fn init_msg() -> anyhow::Error {
@@ -334,11 +1386,104 @@ pub fn init_msg() -> anyhow::Error {
 #[cfg(test)]
 mod test {
 
-    use crate::utils::validate_modname;
+    use std::{fs, path::PathBuf};
+
+    use crate::utils::{Transaction, parse_file_size, suggest, validate_modname};
+
+    /// A fresh profile-like folder with a `packages` dir and an `enabledmods.json`
+    fn scratch_profile(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("papa-test-{name}-{}", std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(dir.join("packages")).unwrap();
+        fs::write(dir.join("enabledmods.json"), "before").unwrap();
+        dir
+    }
 
     #[test]
     fn suceed_validate_modname() {
         let test_name = "foo.bar@0.1.0";
         assert!(validate_modname(test_name).is_ok());
     }
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(parse_file_size("1024").unwrap(), 1024);
+        assert_eq!(parse_file_size("500MB").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_file_size("1.5 GiB").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_file_size("lots").is_err());
+        assert!(parse_file_size("5 parsecs").is_err());
+    }
+
+    #[test]
+    fn suggest_names() {
+        let names = ["Fifty.Server_Utilities", "S2Mods.Spectre", "Fifty.Parkour"];
+        assert_eq!(
+            suggest("fifty.serverutils", names),
+            ["Fifty.Server_Utilities"]
+        );
+        assert_eq!(suggest("spectre", names), ["S2Mods.Spectre"]);
+        assert!(suggest("northstar.client", names).is_empty());
+    }
+
+    #[test]
+    fn transaction_rolls_back() {
+        let dir = scratch_profile("rollback");
+        let packages = dir.join("packages");
+
+        let mut tx = Transaction::begin(&packages, &dir).unwrap();
+        let staged = tx.staging().join("a-b-1.0.0");
+        fs::create_dir(&staged).unwrap();
+        fs::write(staged.join("manifest.json"), "{}").unwrap();
+        tx.stage(&staged);
+        fs::write(dir.join("enabledmods.json"), "after").unwrap();
+        drop(tx);
+
+        assert!(!dir.join(".papa-install-staging").exists());
+        assert_eq!(fs::read_dir(&packages).unwrap().count(), 0);
+        assert_eq!(
+            fs::read_to_string(dir.join("enabledmods.json")).unwrap(),
+            "before"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn transaction_failed_commit_restores() {
+        let dir = scratch_profile("commit");
+        let packages = dir.join("packages");
+        fs::create_dir(packages.join("a-b-1.0.0")).unwrap();
+        fs::write(packages.join("a-b-1.0.0").join("old"), "").unwrap();
+
+        let mut tx = Transaction::begin(&packages, &dir).unwrap();
+        let staged = tx.staging().join("a-b-1.0.0");
+        fs::create_dir(&staged).unwrap();
+        fs::write(staged.join("new"), "").unwrap();
+        tx.stage(&staged);
+        // never created, so moving it into place fails
+        tx.stage(tx.staging().join("c-d-1.0.0"));
+        fs::write(dir.join("enabledmods.json"), "after").unwrap();
+
+        assert!(tx.commit().is_err());
+        assert!(packages.join("a-b-1.0.0").join("old").exists());
+        assert!(!packages.join("a-b-1.0.0").join("new").exists());
+        assert!(!packages.join("c-d-1.0.0").exists());
+        assert!(!dir.join(".papa-install-replaced").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("enabledmods.json")).unwrap(),
+            "before"
+        );
+
+        // a package that doesn't replace anything is simply moved in
+        let mut tx = Transaction::begin(&packages, &dir).unwrap();
+        let staged = tx.staging().join("e-f-1.0.0");
+        fs::create_dir(&staged).unwrap();
+        tx.stage(&staged);
+        assert_eq!(tx.commit().unwrap(), [packages.join("e-f-1.0.0")]);
+        assert!(packages.join("e-f-1.0.0").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }